@@ -0,0 +1,185 @@
+use hyper::Uri;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+use std::time::Duration;
+use std::{fs, io};
+
+/// Configuration file, e.g.
+///
+/// ```toml
+/// [collections.rust]
+/// feeds = [
+///     "https://blog.rust-lang.org/feed.xml",
+///     { url = "https://github.com/rust-lang/rust/releases.atom", label = "Rust releases" },
+///     { url = "https://example.com/feed.xml", refresh = 3600 },
+/// ]
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Named sets of feeds, served at `/c/{name}`.
+    #[serde(default)]
+    pub collections: BTreeMap<String, Collection>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Collection {
+    pub feeds: Vec<FeedConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum FeedConfig {
+    Url(String),
+    Labeled(LabeledFeed),
+}
+
+/// A feed with settings of its own, which are checked so a typo isn't silently ignored.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LabeledFeed {
+    pub url: String,
+    pub label: Option<String>,
+    /// Seconds to keep the feed before fetching it again, overriding its own refresh hints.
+    pub refresh: Option<u64>,
+}
+
+impl FeedConfig {
+    pub fn url(&self) -> &str {
+        match self {
+            FeedConfig::Url(url) => url,
+            FeedConfig::Labeled(feed) => &feed.url,
+        }
+    }
+
+    /// Overrides the feed's own title.
+    pub fn label(&self) -> Option<&str> {
+        match self {
+            FeedConfig::Url(_) => None,
+            FeedConfig::Labeled(feed) => feed.label.as_deref(),
+        }
+    }
+
+    pub fn refresh(&self) -> Option<Duration> {
+        match self {
+            FeedConfig::Url(_) => None,
+            FeedConfig::Labeled(feed) => feed.refresh.map(Duration::from_secs),
+        }
+    }
+}
+
+impl Config {
+    /// Per-feed refresh overrides, keyed by URL as it will be requested.
+    pub fn refresh(&self) -> HashMap<String, Duration> {
+        self.collections
+            .values()
+            .flat_map(|collection| &collection.feeds)
+            .filter_map(|feed| {
+                let url = feed.url().parse::<Uri>().ok()?;
+                Some((url.to_string(), feed.refresh()?))
+            })
+            .collect()
+    }
+
+    /// Hosts of every configured feed.
+    pub fn hosts(&self) -> HashSet<String> {
+        self.collections
+            .values()
+            .flat_map(|collection| &collection.feeds)
+            .filter_map(|feed| Some(feed.url().parse::<Uri>().ok()?.host()?.to_owned()))
+            .collect()
+    }
+}
+
+pub fn load(path: &Path) -> Result<Config, io::Error> {
+    let contents = fs::read_to_string(path)?;
+    let config: Config =
+        toml::from_str(&contents).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    // Catch bad URLs at startup, rather than on every request.
+    for (name, collection) in &config.collections {
+        for feed in &collection.feeds {
+            if let Err(e) = feed.url().parse::<Uri>() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid URL in collection {}: {}: {}", name, feed.url(), e),
+                ));
+            }
+        }
+    }
+
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_collections() {
+        let config: Config = toml::from_str(
+            r#"
+            [collections.rust]
+            feeds = [
+                "https://blog.rust-lang.org/feed.xml",
+                { url = "https://github.com/rust-lang/rust/releases.atom", label = "Rust releases" },
+                { url = "https://example.com/feed.xml", refresh = 3600 },
+            ]
+
+            [collections.empty]
+            feeds = []
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.collections.keys().collect::<Vec<_>>(),
+            ["empty", "rust"]
+        );
+        let feeds = &config.collections["rust"].feeds;
+        assert_eq!(feeds[0].url(), "https://blog.rust-lang.org/feed.xml");
+        assert_eq!(feeds[0].label(), None);
+        assert_eq!(
+            feeds[1].url(),
+            "https://github.com/rust-lang/rust/releases.atom"
+        );
+        assert_eq!(feeds[1].label(), Some("Rust releases"));
+        assert_eq!(feeds[1].refresh(), None);
+        assert_eq!(feeds[2].label(), None);
+        assert_eq!(feeds[2].refresh(), Some(Duration::from_secs(3600)));
+        assert_eq!(
+            config.refresh(),
+            HashMap::from([(
+                String::from("https://example.com/feed.xml"),
+                Duration::from_secs(3600)
+            )])
+        );
+        assert_eq!(
+            config.hosts(),
+            HashSet::from([
+                String::from("blog.rust-lang.org"),
+                String::from("github.com"),
+                String::from("example.com")
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_unknown_field() {
+        let config = toml::from_str::<Config>(
+            r#"
+            [collections.rust]
+            feeds = [{ url = "https://example.com/feed.xml", lable = "Example" }]
+            "#,
+        );
+        assert!(config.is_err());
+    }
+
+    #[test]
+    fn test_parse_empty() {
+        let config: Config = toml::from_str("").unwrap();
+        assert!(config.collections.is_empty());
+    }
+}
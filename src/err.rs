@@ -1,36 +1,93 @@
-use axum::response::IntoResponse;
+use axum::response::{Html, IntoResponse};
 use hyper::StatusCode;
+use quick_xml::escape::escape;
 use std::fmt::{self, Debug, Write};
 
 pub type Error = Box<dyn std::error::Error + Send + Sync + 'static>;
 
-pub struct ResponseError(Error);
+/// An error to show instead of the requested page, with the status to show it with (500 unless specified).
+pub struct ResponseError {
+    status: StatusCode,
+    error: Error,
+}
+
+impl ResponseError {
+    pub fn new(status: StatusCode, e: impl Into<Error>) -> Self {
+        ResponseError {
+            status,
+            error: e.into(),
+        }
+    }
+
+    /// The request itself was invalid, e.g. a malformed query string.
+    pub fn bad_request(e: impl Into<Error>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, e)
+    }
+}
 
 impl<T> From<T> for ResponseError
 where
     T: Into<Error>,
 {
     fn from(e: T) -> Self {
-        ResponseError(e.into())
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, e)
     }
 }
 
 impl Debug for ResponseError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        Debug::fmt(&self.0, f)
+        Debug::fmt(&self.error, f)
     }
 }
 
 impl IntoResponse for ResponseError {
     fn into_response(self) -> axum::response::Response {
-        let mut body = String::from("Error: ");
-        write!(body, "{}", self.0).unwrap();
-        let mut err: &dyn std::error::Error = self.0.as_ref();
+        let mut message = String::from("Error: ");
+        write!(message, "{}", self.error).unwrap();
+        let mut err: &dyn std::error::Error = self.error.as_ref();
         while let Some(source) = err.source() {
-            write!(body, " -> {}", source).unwrap();
+            write!(message, " -> {}", source).unwrap();
             err = source;
         }
 
-        (StatusCode::INTERNAL_SERVER_ERROR, body).into_response()
+        let body = format!(
+            r#"<!DOCTYPE html><html><head><meta charset="utf-8"><title>{status}</title></head><body><h1>{status}</h1><p>{message}</p><p><a href="/">Usage</a></p></body></html>"#,
+            status = self.status,
+            message = escape(&message),
+        );
+
+        (self.status, Html(body)).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http_body_util::BodyExt;
+    use thiserror::Error;
+
+    #[derive(Debug, Error)]
+    #[error("invalid <input>")]
+    struct Inner;
+
+    #[derive(Debug, Error)]
+    #[error("bad request")]
+    struct Outer(#[source] Inner);
+
+    #[tokio::test]
+    async fn test_into_response() {
+        let response = ResponseError::bad_request(Outer(Inner)).into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.contains("<h1>400 Bad Request</h1>"));
+        assert!(body.contains("<p>Error: bad request -&gt; invalid &lt;input&gt;</p>"));
+    }
+
+    #[test]
+    fn test_default_status() {
+        let e: ResponseError = "failed".into();
+        assert_eq!(e.status, StatusCode::INTERNAL_SERVER_ERROR);
     }
 }
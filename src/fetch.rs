@@ -1,27 +1,118 @@
-use crate::err::Error;
-use chrono::{DateTime, Utc};
+use crate::metrics::Metrics;
+use crate::url;
+use chrono::{DateTime, Local, TimeDelta, Utc};
+use feed_rs::model::Entry;
 use http_body_util::{BodyExt, Empty};
-use hyper::body::Bytes;
-use hyper::header::{ACCEPT, USER_AGENT};
-use hyper::{Method, Request, Uri};
+use hyper::body::{Bytes, Incoming};
+use hyper::header::{
+    ACCEPT, ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, ETAG,
+    IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, USER_AGENT,
+};
+use hyper::{Method, Request, StatusCode, Uri};
 use hyper_rustls::HttpsConnector;
 use hyper_util::client::legacy::connect::HttpConnector;
 use hyper_util::client::legacy::Client;
+use mediatype::names::{HTML, TEXT};
+use mediatype::MediaType;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error as _;
+use std::future::Future;
+use std::io;
+use std::net::IpAddr;
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+use std::time::Duration;
 use thiserror::Error;
+use tokio::time::Instant;
 
+mod backoff;
+mod cache;
+mod coalesce;
 mod date;
+mod decode;
 mod extract;
+mod health;
+mod limit;
+mod redirect;
+mod refresh;
+mod ssrf;
 
-pub type FetchClient = Client<HttpsConnector<HttpConnector>, Empty<Bytes>>;
+#[cfg(test)]
+mod tests;
 
-#[derive(Debug)]
+pub use health::FeedHealth;
+pub use ssrf::{Policy, Resolver};
+
+pub type FetchClient = Client<HttpsConnector<HttpConnector<Resolver>>, Empty<Bytes>>;
+
+pub struct Config {
+    /// Maximum number of redirects to follow before giving up.
+    pub max_redirects: usize,
+    /// How long to wait for a response, or for the response body, before giving up.
+    pub read_timeout: Duration,
+    /// Whether to skip bad entries instead of failing the whole feed.
+    pub lenient: bool,
+    /// How long feeds stay fresh, overriding their own refresh hints, keyed by URL.
+    pub refresh: HashMap<String, Duration>,
+    /// Maximum size of a response body, before and after decompression.
+    pub max_body_size: usize,
+    /// Maximum number of requests in flight at once; more wait their turn.
+    pub max_connections: NonZeroUsize,
+    /// Maximum number of requests in flight to any one host.
+    pub max_connections_per_host: NonZeroUsize,
+    /// If set, refuse to fetch from internal addresses, except for the hosts it allows.
+    pub policy: Option<Arc<Policy>>,
+    /// If set, where to record how fetches went.
+    pub metrics: Option<Arc<Metrics>>,
+}
+
+pub struct Fetcher {
+    client: FetchClient,
+    config: Config,
+    cache: cache::Cache,
+    first_seen: cache::FirstSeen,
+    backoff: backoff::Backoff,
+    limits: limit::Limits,
+    in_flight: coalesce::InFlight<(Feed, Vec<Item>), FetchError>,
+    health: health::Health,
+}
+
+impl Fetcher {
+    pub fn new(client: FetchClient, config: Config) -> Self {
+        Self {
+            client,
+            limits: limit::Limits::new(config.max_connections, config.max_connections_per_host),
+            config,
+            cache: Default::default(),
+            first_seen: Default::default(),
+            backoff: Default::default(),
+            in_flight: Default::default(),
+            health: Default::default(),
+        }
+    }
+
+    /// How every feed fetched so far has fared, by the URL it was requested from.
+    pub fn health(&self) -> Vec<(String, FeedHealth)> {
+        self.health.feeds()
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct Feed {
+    /// The URL the feed was actually loaded from, after following redirects.
     pub url: String,
     pub title: String,
     pub logo_url: Option<String>,
+    /// If the feed was permanently redirected, the URL it should be requested from in the future.
+    pub moved_to: Option<String>,
+    /// If the requested URL was an HTML page, the feed URL discovered from its `<link>` tags.
+    pub discovered: Option<String>,
+    /// Problems with individual entries that were skipped or repaired.
+    pub warnings: Vec<String>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Item {
     pub timestamp: DateTime<Utc>,
     pub href: String,
@@ -30,85 +121,745 @@ pub struct Item {
     pub summary: Option<String>,
 }
 
-#[derive(Debug, Error)]
-enum RssError {
-    #[error("Missing timestamp")]
-    MissingTimestamp,
+/// A feed that was fetched and parsed, but is missing something required.
+#[derive(Debug, Clone, Error)]
+pub enum RssError {
     #[error("Missing link")]
     MissingLink,
     #[error("Missing title")]
     MissingTitle,
     #[error("Missing feed title")]
     MissingFeedTitle,
+    #[error("HTML page without a feed link")]
+    MissingFeedLink,
 }
 
-pub async fn rss(client: FetchClient, url: Uri) -> Result<(Feed, Vec<Item>), Error> {
-    let request = Request::builder()
-        .method(Method::GET)
-        .uri(&url)
-        .header(
-            ACCEPT,
-            "application/atom+xml, application/rss+xml, application/xml;q=0.9, text/xml;q=0.8",
-        )
-        .header(
-            USER_AGENT,
-            concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION")),
-        )
-        .body(Default::default())?;
+/// Why a feed or document couldn't be fetched.
+#[derive(Debug, Clone, Error)]
+pub enum FetchError {
+    #[error("Invalid URL: {0}")]
+    InvalidUrl(String),
+    #[error("DNS lookup for {host} failed: {message}")]
+    Dns { host: String, message: String },
+    #[error("Couldn't connect to {host}: {message}")]
+    Connect { host: String, message: String },
+    #[error("TLS error with {host}: {message}")]
+    Tls { host: String, message: String },
+    #[error("Timed out after {}s", .0.as_secs())]
+    Timeout(Duration),
+    #[error("HTTP {status}")]
+    Status { status: StatusCode, snippet: String },
+    #[error("HTTP {0} without Location header")]
+    MissingLocation(StatusCode),
+    #[error("Too many redirects (more than {0})")]
+    TooManyRedirects(usize),
+    #[error("Redirect loop at {0}")]
+    RedirectLoop(Uri),
+    #[error("Refusing to follow redirect from https to {0}")]
+    InsecureRedirect(Uri),
+    #[error("Response body larger than {0} bytes")]
+    BodyTooLarge(usize),
+    #[error("Unsupported Content-Encoding: {0}")]
+    UnsupportedEncoding(String),
+    #[error("Invalid {encoding} body: {message}")]
+    Decompress { encoding: String, message: String },
+    #[error("Invalid feed: {0}")]
+    Parse(String),
+    #[error(transparent)]
+    Validation(#[from] RssError),
+    #[error("Refusing to fetch {host}: {addr} is an internal address")]
+    Blocked { host: String, addr: IpAddr },
+    #[error("Backing off from {host} until {}", .until.with_timezone(&Local).format("%H:%M"))]
+    BackingOff { host: String, until: DateTime<Utc> },
+}
+
+/// Broad categories of [`FetchError`], e.g. for grouping errors on a page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ErrorKind {
+    InvalidUrl,
+    Dns,
+    Connect,
+    Tls,
+    Timeout,
+    HttpStatus,
+    Redirect,
+    BodyTooLarge,
+    Encoding,
+    Parse,
+    Validation,
+    Blocked,
+    BackingOff,
+}
+
+impl ErrorKind {
+    /// A stable, machine-readable name for the kind, e.g. for JSON output.
+    pub fn code(self) -> &'static str {
+        match self {
+            ErrorKind::InvalidUrl => "invalid_url",
+            ErrorKind::Dns => "dns",
+            ErrorKind::Connect => "connect",
+            ErrorKind::Tls => "tls",
+            ErrorKind::Timeout => "timeout",
+            ErrorKind::HttpStatus => "http_status",
+            ErrorKind::Redirect => "redirect",
+            ErrorKind::BodyTooLarge => "body_too_large",
+            ErrorKind::Encoding => "encoding",
+            ErrorKind::Parse => "parse",
+            ErrorKind::Validation => "validation",
+            ErrorKind::Blocked => "blocked",
+            ErrorKind::BackingOff => "backing_off",
+        }
+    }
+
+    /// A heading for errors of this kind.
+    pub fn label(self) -> &'static str {
+        match self {
+            ErrorKind::InvalidUrl => "Invalid URLs",
+            ErrorKind::Dns => "DNS lookup failed",
+            ErrorKind::Connect => "Connection failed",
+            ErrorKind::Tls => "TLS errors",
+            ErrorKind::Timeout => "Timed out",
+            ErrorKind::HttpStatus => "HTTP errors",
+            ErrorKind::Redirect => "Bad redirects",
+            ErrorKind::BodyTooLarge => "Too large",
+            ErrorKind::Encoding => "Undecodable responses",
+            ErrorKind::Parse => "Invalid feeds",
+            ErrorKind::Validation => "Incomplete feeds",
+            ErrorKind::Blocked => "Blocked",
+            ErrorKind::BackingOff => "Rate-limited",
+        }
+    }
+}
+
+impl FetchError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            FetchError::InvalidUrl(_) => ErrorKind::InvalidUrl,
+            FetchError::Dns { .. } => ErrorKind::Dns,
+            FetchError::Connect { .. } => ErrorKind::Connect,
+            FetchError::Tls { .. } => ErrorKind::Tls,
+            FetchError::Timeout(_) => ErrorKind::Timeout,
+            FetchError::Status { .. } => ErrorKind::HttpStatus,
+            FetchError::MissingLocation(_)
+            | FetchError::TooManyRedirects(_)
+            | FetchError::RedirectLoop(_)
+            | FetchError::InsecureRedirect(_) => ErrorKind::Redirect,
+            FetchError::BodyTooLarge(_) => ErrorKind::BodyTooLarge,
+            FetchError::UnsupportedEncoding(_) | FetchError::Decompress { .. } => {
+                ErrorKind::Encoding
+            }
+            FetchError::Parse(_) => ErrorKind::Parse,
+            FetchError::Validation(_) => ErrorKind::Validation,
+            FetchError::Blocked { .. } => ErrorKind::Blocked,
+            FetchError::BackingOff { .. } => ErrorKind::BackingOff,
+        }
+    }
+
+    /// Whether trying again soon might succeed, as opposed to a problem with the feed itself.
+    ///
+    /// Rate limiting isn't worth retrying, since the backoff already decides when to try again.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            FetchError::Dns { .. } | FetchError::Connect { .. } | FetchError::Timeout(_) => true,
+            FetchError::Status { status, .. } => match *status {
+                StatusCode::REQUEST_TIMEOUT => true,
+                StatusCode::NOT_IMPLEMENTED
+                | StatusCode::SERVICE_UNAVAILABLE
+                | StatusCode::HTTP_VERSION_NOT_SUPPORTED => false,
+                status => status.is_server_error(),
+            },
+            _ => false,
+        }
+    }
+}
+
+/// Maximum length of the body snippet included in HTTP errors.
+const SNIPPET_LEN: usize = 200;
+/// How much of an error response's body to download, for its snippet.
+const ERROR_PREFIX_LEN: usize = 4096;
+
+/// Fetch and parse a feed, sharing the result with any concurrent calls for the same URL.
+pub async fn rss(fetcher: &Fetcher, url: Uri) -> Result<(Feed, Vec<Item>), FetchError> {
+    let key = url.to_string();
+    fetcher
+        .in_flight
+        .run(key.clone(), || async {
+            let mut outcome = Outcome {
+                fetcher,
+                url: key,
+                host: host(&url).to_owned(),
+                start: Instant::now(),
+                attempt: health::Attempt::default(),
+                recorded: false,
+            };
+            let result = fetch_rss(fetcher, url, &mut outcome.attempt).await;
+            outcome.record(&result);
+            result
+        })
+        .await
+}
+
+/// Records how a fetch went in the metrics and health, even if it's cancelled (e.g. by a page's deadline).
+struct Outcome<'a> {
+    fetcher: &'a Fetcher,
+    url: String,
+    host: String,
+    start: Instant,
+    attempt: health::Attempt,
+    recorded: bool,
+}
+
+impl Outcome<'_> {
+    fn record(&mut self, result: &Result<(Feed, Vec<Item>), FetchError>) {
+        self.recorded = true;
+        let elapsed = self.start.elapsed();
+        let attempt = std::mem::take(&mut self.attempt);
+
+        if let Some(metrics) = &self.fetcher.config.metrics {
+            if attempt.requested {
+                metrics.fetch(&self.host, elapsed);
+            }
+            match result {
+                // Anything not downloaded came from the cache, even if it had to be revalidated.
+                Ok(_) => metrics.cache(attempt.bytes.is_none()),
+                Err(e) => metrics.fetch_error(e.kind()),
+            }
+        }
+        self.fetcher.health.record(
+            std::mem::take(&mut self.url),
+            elapsed,
+            attempt,
+            result,
+            Utc::now(),
+        );
+    }
+}
+
+impl Drop for Outcome<'_> {
+    fn drop(&mut self) {
+        if !self.recorded {
+            self.record(&Err(FetchError::Timeout(self.start.elapsed())));
+        }
+    }
+}
+
+async fn fetch_rss(
+    fetcher: &Fetcher,
+    url: Uri,
+    attempt: &mut health::Attempt,
+) -> Result<(Feed, Vec<Item>), FetchError> {
+    let requested_url = url.to_string();
+    let cached = fetcher.cache.get(&requested_url);
+
+    if let Some(cached) = &cached {
+        // Don't fetch again before the feed asked us to...
+        if Utc::now() < cached.fresh_until {
+            tracing::debug!("feed fresh until {}: {}", cached.fresh_until, requested_url);
+            return Ok((cached.feed.clone(), cached.items.clone()));
+        }
+        // ...or while its host is rate-limiting us.
+        if let Some(until) = fetcher.backoff.until(host(&url)) {
+            tracing::debug!("backing off until {}: {}", until, requested_url);
+            return Ok((cached.feed.clone(), cached.items.clone()));
+        }
+    }
+
+    attempt.requested = true;
+    let Response {
+        mut url,
+        mut moved_to,
+        mut response,
+        mut permit,
+    } = get(fetcher, url, cached.as_deref()).await?;
+    attempt.status = Some(response.status());
+
+    // Given a web page rather than a feed, look for the feed it links to.
+    let mut discovered = None;
+    if response.status().is_success() && is_html(&response) {
+        let html = body(fetcher, &url, response).await?;
+        drop(permit);
+        let link = extract::feed_link(&String::from_utf8_lossy(&html))
+            .map_err(|e| FetchError::Parse(e.to_string()))?
+            .ok_or(RssError::MissingFeedLink)?;
+        let link = url::resolve(&url.to_string(), &link);
+        tracing::info!("discovered feed {} on {}", link, url);
+
+        let feed_moved_to;
+        Response {
+            url,
+            moved_to: feed_moved_to,
+            response,
+            permit,
+        } = get(fetcher, parse_url(&link)?, cached.as_deref()).await?;
+        attempt.status = Some(response.status());
+        discovered = Some(link);
+        // The page having moved still matters if the feed itself didn't.
+        moved_to = feed_moved_to.or(moved_to);
+    }
+
+    // Keep showing what we have while the host is rate-limiting us.
+    if let Some(cached) = cached
+        .as_ref()
+        .filter(|_| fetcher.backoff.until(host(&url)).is_some())
+    {
+        return Ok((cached.feed.clone(), cached.items.clone()));
+    }
+
+    let rss_url = url;
+    let url = rss_url.to_string();
+    let etag = response.headers().get(ETAG).cloned();
+    let last_modified = response.headers().get(LAST_MODIFIED).cloned();
+    let max_age = refresh::max_age(response.headers());
+
+    if response.status() == StatusCode::NOT_MODIFIED {
+        if let Some(cached) = cached.filter(|c| c.feed.url == url) {
+            tracing::debug!("feed not modified: {}", url);
+            let feed = Feed {
+                moved_to,
+                discovered,
+                ..cached.feed.clone()
+            };
+            fetcher.cache.insert(
+                requested_url.clone(),
+                cache::Entry {
+                    etag: etag.or_else(|| cached.etag.clone()),
+                    last_modified: last_modified.or_else(|| cached.last_modified.clone()),
+                    fresh_until: fresh_until(fetcher, &requested_url, &cached.hints, max_age),
+                    hints: cached.hints.clone(),
+                    feed: feed.clone(),
+                    items: cached.items.clone(),
+                },
+            );
+            return Ok((feed, cached.items.clone()));
+        }
+    }
+
+    let rss = body(fetcher, &rss_url, response).await?;
+    drop(permit);
+    attempt.bytes = Some(rss.len());
+
+    let (mut feed, items) = parse(
+        url,
+        moved_to,
+        &rss,
+        &fetcher.first_seen,
+        fetcher.config.lenient,
+    )?;
+    feed.discovered = discovered;
+
+    if let Some(moved_to) = &feed.moved_to {
+        tracing::info!("feed permanently moved to {}", moved_to);
+    }
+
+    let hints = refresh::hints(&rss);
+    fetcher.cache.insert(
+        requested_url.clone(),
+        cache::Entry {
+            etag,
+            last_modified,
+            fresh_until: fresh_until(fetcher, &requested_url, &hints, max_age),
+            hints,
+            feed: feed.clone(),
+            items: items.clone(),
+        },
+    );
+
+    Ok((feed, items))
+}
+
+/// When a feed should next be fetched, from its own hints, unless they're overridden.
+fn fresh_until(
+    fetcher: &Fetcher,
+    requested_url: &str,
+    hints: &refresh::Hints,
+    max_age: Option<Duration>,
+) -> DateTime<Utc> {
+    let now = Utc::now();
+    match fetcher.config.refresh.get(requested_url) {
+        Some(refresh) => now + TimeDelta::from_std(*refresh).unwrap_or_default(),
+        None => hints.fresh_until(max_age, now),
+    }
+}
+
+/// Fetch some other document (e.g. an OPML file), following redirects like [`rss`].
+pub async fn document(fetcher: &Fetcher, url: Uri) -> Result<Bytes, FetchError> {
+    let Response {
+        url,
+        response,
+        permit,
+        ..
+    } = get(fetcher, url, None).await?;
+    let body = body(fetcher, &url, response).await;
+    drop(permit);
+    body
+}
+
+/// A response, after following redirects.
+struct Response {
+    /// The URL the response was actually loaded from.
+    url: Uri,
+    /// If the URL was permanently redirected, where it should be requested from in the future.
+    moved_to: Option<String>,
+    response: hyper::Response<Incoming>,
+    /// Held until the body has been read.
+    permit: limit::Permit,
+}
+
+async fn get(
+    fetcher: &Fetcher,
+    mut url: Uri,
+    cached: Option<&cache::Entry>,
+) -> Result<Response, FetchError> {
+    let mut redirects = redirect::Redirects::new(fetcher.config.max_redirects);
+
+    loop {
+        if let Some(until) = fetcher.backoff.until(host(&url)) {
+            let host = host(&url).to_owned();
+            return Err(FetchError::BackingOff { host, until });
+        }
+
+        // Host names are checked as they're resolved, but addresses aren't resolved.
+        if let Some(policy) = &fetcher.config.policy {
+            policy.check_literal(host(&url))?;
+        }
+
+        let permit = fetcher.limits.acquire(host(&url)).await;
+
+        // Validators only apply to the URL the cached feed was actually loaded from.
+        let validators = cached.filter(|c| c.feed.url == url.to_string());
+        let response = read(fetcher, fetcher.client.request(request(&url, validators)?))
+            .await?
+            .map_err(|e| connect_error(&url, e))?;
 
-    let response = client.request(request).await?;
+        let status = response.status();
+        if matches!(
+            status,
+            StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE
+        ) {
+            let retry_after = backoff::retry_after(response.headers(), Utc::now());
+            let until = fetcher.backoff.failure(host(&url), retry_after);
+            tracing::info!("HTTP {} from {}, backing off until {}", status, url, until);
+        } else {
+            fetcher.backoff.success(host(&url));
+        }
 
-    let url = url.to_string();
-    let rss = response.into_body().collect().await?.to_bytes();
+        let Some(next) = redirects.next(url.clone(), status, response.headers())? else {
+            return Ok(Response {
+                url,
+                moved_to: redirects.moved_to,
+                response,
+                permit,
+            });
+        };
+
+        tracing::debug!("following {} redirect to {}", status, next);
+        url = next;
+    }
+}
+
+/// Classify a failure to get a response by walking its causes, since the client only says it couldn't connect.
+fn connect_error(url: &Uri, e: hyper_util::client::legacy::Error) -> FetchError {
+    let causes = || std::iter::successors(e.source(), |&e| e.source());
+
+    // Refused by the `Resolver`.
+    if let Some(blocked) = causes().find_map(|e| e.downcast_ref::<FetchError>()) {
+        return blocked.clone();
+    }
+
+    let host = host(url).to_owned();
+    if let Some(e) = causes().find_map(tls_error) {
+        let message = e.to_string();
+        return FetchError::Tls { host, message };
+    }
+
+    // hyper-util's connection errors aren't exported, so recognize DNS failures by message.
+    let message = causes()
+        .last()
+        .map_or_else(|| e.to_string(), |e| e.to_string());
+    if causes().any(|e| e.to_string() == "dns error") {
+        FetchError::Dns { host, message }
+    } else {
+        FetchError::Connect { host, message }
+    }
+}
+
+/// Find a TLS handshake failure, which tokio-rustls wraps in (possibly several) I/O errors that don't expose it as a source.
+fn tls_error<'a>(mut e: &'a (dyn std::error::Error + 'static)) -> Option<&'a rustls::Error> {
+    loop {
+        if let Some(e) = e.downcast_ref::<rustls::Error>() {
+            return Some(e);
+        }
+        e = e.downcast_ref::<io::Error>()?.get_ref()?;
+    }
+}
+
+/// Parse a URL taken from a feed or response, e.g. a redirect target.
+fn parse_url(url: &str) -> Result<Uri, FetchError> {
+    url.parse()
+        .map_err(|e| FetchError::InvalidUrl(format!("{url}: {e}")))
+}
+
+fn host(url: &Uri) -> &str {
+    url.host().unwrap_or_default()
+}
+
+fn is_html(response: &hyper::Response<Incoming>) -> bool {
+    response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .and_then(|content_type| MediaType::parse(content_type).ok())
+        .is_some_and(|media_type| media_type.ty == TEXT && media_type.subty == HTML)
+}
+
+/// Read a successful response's body.
+async fn body(
+    fetcher: &Fetcher,
+    url: &Uri,
+    response: hyper::Response<Incoming>,
+) -> Result<Bytes, FetchError> {
+    let status = response.status();
+    let max_size = fetcher.config.max_body_size;
+    let encoding = response
+        .headers()
+        .get(CONTENT_ENCODING)
+        .map(|encoding| String::from_utf8_lossy(encoding.as_bytes()).into_owned());
+
+    // An error page is only worth a snippet, however large it is or however it's encoded.
+    if !status.is_success() {
+        let body = read(fetcher, prefix(response.into_body(), ERROR_PREFIX_LEN))
+            .await
+            .unwrap_or_default();
+        if let Some(metrics) = &fetcher.config.metrics {
+            metrics.downloaded(body.len());
+        }
+        let body = decode::prefix(encoding.as_deref(), &body, ERROR_PREFIX_LEN);
+        return Err(FetchError::Status {
+            status,
+            snippet: snippet(&body),
+        });
+    }
+
+    // Don't bother downloading a body that's declared to be too large.
+    let content_length = response
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|length| length.to_str().ok()?.parse::<usize>().ok());
+    if content_length.is_some_and(|length| length > max_size) {
+        return Err(FetchError::BodyTooLarge(max_size));
+    }
+
+    let body = read(fetcher, collect(url, response.into_body(), max_size)).await??;
+    if let Some(metrics) = &fetcher.config.metrics {
+        metrics.downloaded(body.len());
+    }
+
+    // Decompressing a large body takes a while, so keep it off the async workers.
+    match encoding {
+        None => Ok(body),
+        Some(encoding) => {
+            tokio::task::spawn_blocking(move || decode::decode(Some(&encoding), body, max_size))
+                .await
+                .unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()))
+        }
+    }
+}
 
+/// Collect the start of a body, up to about `len` bytes, keeping what arrived if the rest fails.
+async fn prefix(mut body: Incoming, len: usize) -> Bytes {
+    let mut collected = Vec::new();
+    while collected.len() < len {
+        let Some(Ok(frame)) = body.frame().await else {
+            break;
+        };
+        if let Ok(data) = frame.into_data() {
+            collected.extend_from_slice(&data);
+        }
+    }
+    Bytes::from(collected)
+}
+
+/// Collect a body, giving up as soon as it's larger than `max_size`.
+async fn collect(url: &Uri, mut body: Incoming, max_size: usize) -> Result<Bytes, FetchError> {
+    let mut collected = Vec::new();
+    while let Some(frame) = body.frame().await {
+        let frame = frame.map_err(|e| FetchError::Connect {
+            host: host(url).to_owned(),
+            message: e.to_string(),
+        })?;
+        if let Ok(data) = frame.into_data() {
+            if collected.len() + data.len() > max_size {
+                return Err(FetchError::BodyTooLarge(max_size));
+            }
+            collected.extend_from_slice(&data);
+        }
+    }
+    Ok(Bytes::from(collected))
+}
+
+fn parse(
+    url: String,
+    moved_to: Option<String>,
+    rss: &[u8],
+    first_seen: &cache::FirstSeen,
+    lenient: bool,
+) -> Result<(Feed, Vec<Item>), FetchError> {
     let parser = feed_rs::parser::Builder::new()
         .base_uri(Some(&url))
         .timestamp_parser(date::parse_date)
         .build();
 
-    let raw_feed = parser.parse(&*rss)?;
+    let raw_feed = parser
+        .parse(rss)
+        .map_err(|e| FetchError::Parse(e.to_string()))?;
 
-    let feed = Feed {
+    let mut feed = Feed {
         url,
         title: raw_feed.title.ok_or(RssError::MissingFeedTitle)?.content,
         logo_url: raw_feed.logo.map(|l| l.uri),
+        moved_to,
+        discovered: None,
+        warnings: Vec::new(),
     };
 
-    let items = raw_feed
-        .entries
-        .into_iter()
-        .map(|item| {
-            let timestamp = item
-                .published
-                .or(item.updated)
-                .ok_or(RssError::MissingTimestamp)?;
-            let href = item
-                .links
-                .into_iter()
-                .next()
-                .ok_or(RssError::MissingLink)?
-                .href;
-            let title = item.title.ok_or(RssError::MissingTitle)?.content;
-            let thumbnail_url = item
-                .media
-                .into_iter()
-                .next()
-                .and_then(|m| m.thumbnails.into_iter().next())
-                .map(|t| t.image.uri);
-            let summary = extract::summary(&href, item.summary, item.content)?;
-
-            Ok(Item {
-                timestamp,
-                href,
-                title,
-                thumbnail_url,
-                summary,
-            })
-        })
-        .collect::<Result<Vec<_>, Error>>()?;
+    let mut items = Vec::new();
+    for (i, entry) in raw_feed.entries.into_iter().enumerate() {
+        let id = if entry.id.is_empty() {
+            format!("#{}", i + 1)
+        } else {
+            entry.id.clone()
+        };
+        match item(
+            entry,
+            raw_feed.updated,
+            first_seen,
+            lenient,
+            &mut feed.warnings,
+        ) {
+            Ok(item) => items.push(item),
+            // In lenient mode, skip bad entries rather than losing the whole feed.
+            Err(e) if lenient => {
+                tracing::debug!("skipping entry {}: {}", id, e);
+                feed.warnings.push(format!("Skipped entry {}: {}", id, e));
+            }
+            Err(e) => return Err(e),
+        }
+    }
 
     tracing::debug!("parsed feed: {:#?}", feed);
     tracing::debug!("first item: {:#?}", items.first());
 
     Ok((feed, items))
 }
+
+fn item(
+    entry: Entry,
+    feed_updated: Option<DateTime<Utc>>,
+    first_seen: &cache::FirstSeen,
+    lenient: bool,
+    warnings: &mut Vec<String>,
+) -> Result<Item, FetchError> {
+    let href = match entry.links.into_iter().next() {
+        Some(link) => link.href,
+        // Atom ids and RSS guids are often permalinks.
+        None if url::is_absolute(&entry.id) => entry.id,
+        None => return Err(RssError::MissingLink.into()),
+    };
+    let timestamp = match entry.published.or(entry.updated) {
+        Some(timestamp) => timestamp,
+        // Remember the fallback timestamp, so the item doesn't move around on every refresh.
+        None => first_seen.get_or_insert(&href, || feed_updated.unwrap_or_else(Utc::now)),
+    };
+    let thumbnail_url = entry
+        .media
+        .into_iter()
+        .next()
+        .and_then(|m| m.thumbnails.into_iter().next())
+        .map(|t| t.image.uri);
+    let content_text = match (&entry.title, &entry.content) {
+        // Only needed as a fallback title; best-effort.
+        (None, Some(content)) => extract::content_text(content).ok().flatten(),
+        _ => None,
+    };
+    let mut summary = match extract::summary(&href, entry.summary, entry.content) {
+        Ok(summary) => summary,
+        // The summary is optional, so keep the item without it.
+        Err(e) if lenient => {
+            warnings.push(format!("Dropped summary of {}: {}", href, e));
+            None
+        }
+        Err(e) => return Err(FetchError::Parse(e.to_string())),
+    };
+    let title = match entry.title {
+        Some(title) => title.content,
+        // Microblog-style feeds often omit titles, so use the start of the text instead.
+        None => {
+            let text = summary
+                .as_deref()
+                .or(content_text.as_deref())
+                .ok_or(RssError::MissingTitle)?;
+            let title = extract::title(text);
+            // Don't repeat the summary if all of it ended up in the title.
+            if summary.as_deref() == Some(&title) {
+                summary = None;
+            }
+            title
+        }
+    };
+
+    Ok(Item {
+        timestamp,
+        href,
+        title,
+        thumbnail_url,
+        summary,
+    })
+}
+
+/// Bound a network operation by the configured read timeout.
+async fn read<T>(fetcher: &Fetcher, fut: impl Future<Output = T>) -> Result<T, FetchError> {
+    let read_timeout = fetcher.config.read_timeout;
+    tokio::time::timeout(read_timeout, fut)
+        .await
+        .map_err(|_| FetchError::Timeout(read_timeout))
+}
+
+fn request(
+    url: &Uri,
+    validators: Option<&cache::Entry>,
+) -> Result<Request<Empty<Bytes>>, FetchError> {
+    let mut request = Request::builder()
+        .method(Method::GET)
+        .uri(url)
+        .header(
+            ACCEPT,
+            "application/atom+xml, application/rss+xml, application/feed+json, application/xml;q=0.9, text/xml;q=0.8",
+        )
+        .header(ACCEPT_ENCODING, decode::ACCEPT_ENCODING)
+        .header(
+            USER_AGENT,
+            concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION")),
+        );
+    if let Some(validators) = validators {
+        if let Some(etag) = &validators.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &validators.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+    request
+        .body(Default::default())
+        .map_err(|e| FetchError::InvalidUrl(format!("{url}: {e}")))
+}
+
+/// Produce a short, whitespace-collapsed excerpt of a response body, for error messages.
+fn snippet(body: &[u8]) -> String {
+    let body = String::from_utf8_lossy(body);
+    let mut snippet = body.split_whitespace().collect::<Vec<_>>().join(" ");
+    if let Some((end, _)) = snippet.char_indices().nth(SNIPPET_LEN) {
+        snippet.truncate(end);
+        snippet.push('…');
+    }
+    snippet
+}
@@ -0,0 +1,133 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use hyper::header::RETRY_AFTER;
+use hyper::HeaderMap;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Backoff after a host's first failure, without a `Retry-After`, doubling for each failure after that.
+const BASE_DELAY: Duration = Duration::from_secs(60);
+/// Longest backoff from repeated failures. A longer `Retry-After` is still honored.
+const MAX_DELAY: Duration = Duration::from_secs(60 * 60);
+
+/// Hosts that asked us to slow down, keyed by host.
+///
+/// Only rate limiting (HTTP 429 and 503) counts as a failure here. Other server errors and failures to
+/// connect aren't the host asking for less traffic, and are retried (or not) by the caller instead.
+#[derive(Default)]
+pub struct Backoff {
+    hosts: Mutex<HashMap<String, Host>>,
+}
+
+struct Host {
+    until: DateTime<Utc>,
+    /// Consecutive failures, which make the backoff grow.
+    failures: u32,
+}
+
+impl Backoff {
+    /// Until when to leave a host alone, if it's currently backing off.
+    pub fn until(&self, host: &str) -> Option<DateTime<Utc>> {
+        let hosts = self.hosts.lock().unwrap();
+        let until = hosts.get(host)?.until;
+        (Utc::now() < until).then_some(until)
+    }
+
+    /// Back off from a host after it rate-limited us, returning until when.
+    pub fn failure(&self, host: &str, retry_after: Option<Duration>) -> DateTime<Utc> {
+        let mut hosts = self.hosts.lock().unwrap();
+        let host = hosts.entry(host.to_owned()).or_insert(Host {
+            until: Utc::now(),
+            failures: 0,
+        });
+        host.failures += 1;
+
+        let exponential = BASE_DELAY
+            .saturating_mul(2u32.saturating_pow(host.failures - 1))
+            .min(MAX_DELAY);
+        let delay = retry_after.unwrap_or_default().max(exponential);
+        host.until = Utc::now() + TimeDelta::from_std(delay).unwrap_or(TimeDelta::days(1));
+        host.until
+    }
+
+    /// Forget a host's failures once it responds with anything other than rate limiting.
+    pub fn success(&self, host: &str) {
+        self.hosts.lock().unwrap().remove(host);
+    }
+}
+
+/// Parse a `Retry-After` header, which is either a number of seconds or an HTTP date.
+pub fn retry_after(headers: &HeaderMap, now: DateTime<Utc>) -> Option<Duration> {
+    let retry_after = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = retry_after.parse() {
+        return Some(Duration::from_secs(secs));
+    }
+    let date = DateTime::parse_from_rfc2822(retry_after).ok()?;
+    Some((date.to_utc() - now).to_std().unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use hyper::header::HeaderValue;
+
+    #[test]
+    fn test_retry_after() {
+        let now = Utc.with_ymd_and_hms(2015, 10, 21, 7, 0, 0).unwrap();
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers, now), None);
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("120"));
+        assert_eq!(retry_after(&headers, now), Some(Duration::from_secs(120)));
+
+        headers.insert(
+            RETRY_AFTER,
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+        );
+        assert_eq!(
+            retry_after(&headers, now),
+            Some(Duration::from_secs(28 * 60))
+        );
+
+        headers.insert(
+            RETRY_AFTER,
+            HeaderValue::from_static("Wed, 21 Oct 2015 06:00:00 GMT"),
+        );
+        assert_eq!(retry_after(&headers, now), Some(Duration::ZERO));
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("soon"));
+        assert_eq!(retry_after(&headers, now), None);
+    }
+
+    #[test]
+    fn test_backoff() {
+        let backoff = Backoff::default();
+        assert_eq!(backoff.until("example.com"), None);
+
+        let delay =
+            |until: DateTime<Utc>| (until - Utc::now() + TimeDelta::seconds(1)).num_minutes();
+
+        // Repeated failures back off exponentially...
+        assert_eq!(delay(backoff.failure("example.com", None)), 1);
+        assert_eq!(delay(backoff.failure("example.com", None)), 2);
+        assert_eq!(delay(backoff.failure("example.com", None)), 4);
+        assert!(backoff.until("example.com").is_some());
+        assert_eq!(backoff.until("example.net"), None);
+
+        // ...unless the host asks for longer...
+        let retry_after = Some(Duration::from_secs(30 * 60));
+        assert_eq!(delay(backoff.failure("example.com", retry_after)), 30);
+
+        // ...up to a limit.
+        for _ in 0..10 {
+            backoff.failure("example.com", None);
+        }
+        assert_eq!(delay(backoff.failure("example.com", None)), 60);
+
+        // A normal response resets it.
+        backoff.success("example.com");
+        assert_eq!(backoff.until("example.com"), None);
+        assert_eq!(delay(backoff.failure("example.com", None)), 1);
+    }
+}
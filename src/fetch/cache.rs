@@ -0,0 +1,150 @@
+use crate::fetch::{refresh, Feed, Item};
+use chrono::{DateTime, TimeDelta, Utc};
+use hyper::header::HeaderValue;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// How long past its freshness an entry is kept for revalidation, before it's evicted.
+const EXPIRE_AFTER: TimeDelta = TimeDelta::days(7);
+/// How long a link that no longer appears in any feed keeps its first-seen timestamp.
+const FORGET_AFTER: TimeDelta = TimeDelta::days(30);
+/// How often to look for first-seen timestamps to forget.
+const SWEEP_INTERVAL: TimeDelta = TimeDelta::hours(1);
+
+/// A previously parsed feed, along with when and how to revalidate it.
+pub struct Entry {
+    pub etag: Option<HeaderValue>,
+    pub last_modified: Option<HeaderValue>,
+    /// Until when the feed can be used without fetching it again.
+    pub fresh_until: DateTime<Utc>,
+    /// The feed's own refresh hints, for when it's revalidated without being downloaded again.
+    pub hints: refresh::Hints,
+    pub feed: Feed,
+    pub items: Vec<Item>,
+}
+
+/// Parsed feeds, keyed by the URL they were requested from.
+#[derive(Default)]
+pub struct Cache {
+    entries: Mutex<HashMap<String, Arc<Entry>>>,
+}
+
+impl Cache {
+    pub fn get(&self, url: &str) -> Option<Arc<Entry>> {
+        self.entries.lock().unwrap().get(url).cloned()
+    }
+
+    /// Insert an entry, evicting any that went stale long ago (e.g. feeds that are no longer requested).
+    pub fn insert(&self, url: String, entry: Entry) {
+        let mut entries = self.entries.lock().unwrap();
+        let expired = Utc::now() - EXPIRE_AFTER;
+        entries.retain(|_, entry| entry.fresh_until > expired);
+        entries.insert(url, Arc::new(entry));
+    }
+}
+
+/// When items without their own timestamp were first seen, keyed by link.
+#[derive(Default)]
+pub struct FirstSeen {
+    timestamps: Mutex<Timestamps>,
+}
+
+#[derive(Default)]
+struct Timestamps {
+    links: HashMap<String, Seen>,
+    swept: Option<DateTime<Utc>>,
+}
+
+struct Seen {
+    first: DateTime<Utc>,
+    last: DateTime<Utc>,
+}
+
+impl FirstSeen {
+    pub fn get_or_insert(
+        &self,
+        href: &str,
+        default: impl FnOnce() -> DateTime<Utc>,
+    ) -> DateTime<Utc> {
+        let now = Utc::now();
+        let mut timestamps = self.timestamps.lock().unwrap();
+
+        // Forget links that haven't been seen in a while, at most once per interval.
+        if timestamps
+            .swept
+            .is_none_or(|swept| now - swept > SWEEP_INTERVAL)
+        {
+            timestamps
+                .links
+                .retain(|_, seen| now - seen.last < FORGET_AFTER);
+            timestamps.swept = Some(now);
+        }
+
+        let seen = timestamps
+            .links
+            .entry(href.to_owned())
+            .or_insert_with(|| Seen {
+                first: default(),
+                last: now,
+            });
+        seen.last = now;
+        seen.first
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(fresh_until: DateTime<Utc>) -> Entry {
+        Entry {
+            etag: None,
+            last_modified: None,
+            fresh_until,
+            hints: Default::default(),
+            feed: Default::default(),
+            items: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_cache_eviction() {
+        let cache = Cache::default();
+        let now = Utc::now();
+        cache.insert(String::from("old"), entry(now - TimeDelta::days(30)));
+        cache.insert(String::from("stale"), entry(now - TimeDelta::hours(1)));
+        cache.insert(String::from("fresh"), entry(now + TimeDelta::hours(1)));
+
+        assert!(cache.get("old").is_none());
+        assert!(cache.get("stale").is_some());
+        assert!(cache.get("fresh").is_some());
+    }
+
+    #[test]
+    fn test_first_seen_eviction() {
+        let first_seen = FirstSeen::default();
+        let then = Utc::now() - TimeDelta::days(60);
+        first_seen.get_or_insert("https://example.com/1", || then);
+        first_seen.get_or_insert("https://example.com/2", || then);
+
+        // The first link stops appearing in its feed.
+        {
+            let mut timestamps = first_seen.timestamps.lock().unwrap();
+            timestamps
+                .links
+                .get_mut("https://example.com/1")
+                .unwrap()
+                .last = then;
+            timestamps.swept = Some(then);
+        }
+        let now = Utc::now();
+        assert_eq!(
+            first_seen.get_or_insert("https://example.com/2", || now),
+            then
+        );
+        assert_eq!(
+            first_seen.get_or_insert("https://example.com/1", || now),
+            now
+        );
+    }
+}
@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use tokio::sync::OnceCell;
+
+type Call<T, E> = Arc<OnceCell<Result<T, E>>>;
+
+/// Calls in progress, so concurrent calls with the same key can share one result.
+pub struct InFlight<T, E> {
+    calls: Mutex<HashMap<String, Call<T, E>>>,
+}
+
+impl<T, E> Default for InFlight<T, E> {
+    fn default() -> Self {
+        Self {
+            calls: Default::default(),
+        }
+    }
+}
+
+impl<T: Clone, E: Clone> InFlight<T, E> {
+    /// Run `f`, unless a call with the same key is already running, in which case wait for its result instead.
+    pub async fn run<F>(&self, key: String, f: impl FnOnce() -> F) -> Result<T, E>
+    where
+        F: Future<Output = Result<T, E>>,
+    {
+        let call = Arc::clone(self.calls.lock().unwrap().entry(key.clone()).or_default());
+        let done = Done {
+            calls: &self.calls,
+            key,
+            call,
+        };
+
+        // If the caller running `f` is cancelled, one of the waiting callers runs its own instead.
+        done.call.get_or_init(f).await.clone()
+    }
+}
+
+/// Forgets a call once it's finished, or once it's cancelled with no other callers waiting on it.
+struct Done<'a, T, E> {
+    calls: &'a Mutex<HashMap<String, Call<T, E>>>,
+    key: String,
+    call: Call<T, E>,
+}
+
+impl<T, E> Drop for Done<'_, T, E> {
+    fn drop(&mut self) {
+        // Later calls start afresh.
+        let mut calls = self.calls.lock().unwrap();
+        if calls
+            .get(&self.key)
+            .is_some_and(|c| Arc::ptr_eq(c, &self.call))
+            && (self.call.initialized() || Arc::strong_count(&self.call) == 2)
+        {
+            calls.remove(&self.key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+    use tokio::time;
+
+    #[tokio::test]
+    async fn test_coalesce() {
+        let in_flight = InFlight::<_, ()>::default();
+        let calls = AtomicUsize::new(0);
+        let call = |key: &str| {
+            in_flight.run(key.to_owned(), || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                time::sleep(Duration::from_millis(10)).await;
+                Ok(calls.load(Ordering::SeqCst))
+            })
+        };
+
+        let (a, b, c) = tokio::join!(call("a"), call("a"), call("b"));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(a.unwrap(), b.unwrap());
+        assert!(c.is_ok());
+
+        // Once the call finishes, the next one runs again.
+        call("a").await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_shared_error() {
+        let in_flight = InFlight::<(), _>::default();
+        let calls = AtomicUsize::new(0);
+        let call = || {
+            in_flight.run(String::from("a"), || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                time::sleep(Duration::from_millis(10)).await;
+                Err(String::from("failed"))
+            })
+        };
+
+        let (a, b) = tokio::join!(call(), call());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(a.unwrap_err(), "failed");
+        assert_eq!(b.unwrap_err(), "failed");
+    }
+
+    #[tokio::test]
+    async fn test_cancelled() {
+        let in_flight = InFlight::<_, ()>::default();
+
+        // The first call is abandoned partway through...
+        let first = in_flight.run(String::from("a"), || async {
+            time::sleep(Duration::from_secs(60)).await;
+            Ok(1)
+        });
+        assert!(time::timeout(Duration::from_millis(10), first)
+            .await
+            .is_err());
+        assert!(in_flight.calls.lock().unwrap().is_empty());
+
+        // ...so the next one runs its own.
+        let second = in_flight.run(String::from("a"), || async { Ok(2) });
+        assert_eq!(second.await.unwrap(), 2);
+    }
+}
@@ -0,0 +1,140 @@
+use crate::fetch::FetchError;
+use flate2::read::{DeflateDecoder, GzDecoder, ZlibDecoder};
+use hyper::body::Bytes;
+use std::io::{self, Read};
+
+/// Encodings we can decode, for `Accept-Encoding`.
+pub const ACCEPT_ENCODING: &str = "gzip, deflate, br";
+
+/// Decode a body according to its `Content-Encoding`, refusing to decompress it past `max_size`.
+pub fn decode(encoding: Option<&str>, body: Bytes, max_size: usize) -> Result<Bytes, FetchError> {
+    let encoding = encoding.map(|e| e.trim().to_ascii_lowercase());
+    let decoded = match encoding.as_deref() {
+        None | Some("" | "identity") => return Ok(body),
+        Some("gzip" | "x-gzip") => read(GzDecoder::new(&body[..]), max_size),
+        // Supposed to be zlib-wrapped, but some servers send raw deflate.
+        Some("deflate") => read(ZlibDecoder::new(&body[..]), max_size)
+            .or_else(|_| read(DeflateDecoder::new(&body[..]), max_size)),
+        Some("br") => read(brotli::Decompressor::new(&body[..], 4096), max_size),
+        Some(encoding) => return Err(FetchError::UnsupportedEncoding(encoding.to_owned())),
+    };
+    let decoded = decoded.map_err(|e| FetchError::Decompress {
+        encoding: encoding.unwrap_or_default(),
+        message: e.to_string(),
+    })?;
+    if decoded.len() > max_size {
+        return Err(FetchError::BodyTooLarge(max_size));
+    }
+    Ok(Bytes::from(decoded))
+}
+
+/// Decode as much of the start of a body as possible, up to `len` bytes, e.g. for a snippet of a truncated error page.
+pub fn prefix(encoding: Option<&str>, body: &[u8], len: usize) -> Vec<u8> {
+    let encoding = encoding.map(|e| e.trim().to_ascii_lowercase());
+    let decoder: Box<dyn Read + '_> = match encoding.as_deref() {
+        None | Some("" | "identity") => Box::new(body),
+        Some("gzip" | "x-gzip") => Box::new(GzDecoder::new(body)),
+        Some("deflate") => Box::new(ZlibDecoder::new(body)),
+        Some("br") => Box::new(brotli::Decompressor::new(body, 4096)),
+        Some(_) => return Vec::new(),
+    };
+    // A truncated stream fails at its end, after what was decoded so far is kept.
+    let mut decoded = Vec::new();
+    let _ = decoder.take(len as u64).read_to_end(&mut decoded);
+    decoded
+}
+
+/// Read up to one byte past `max_size`, so the caller can tell whether it was exceeded.
+fn read(decoder: impl Read, max_size: usize) -> Result<Vec<u8>, io::Error> {
+    let mut decoded = Vec::new();
+    decoder
+        .take(max_size as u64 + 1)
+        .read_to_end(&mut decoded)?;
+    Ok(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::{DeflateEncoder, GzEncoder, ZlibEncoder};
+    use flate2::Compression;
+    use std::io::Write;
+
+    const FEED: &[u8] = b"<rss><channel><title>Test</title></channel></rss>";
+
+    fn compress(mut encoder: impl Write) {
+        encoder.write_all(FEED).unwrap();
+    }
+
+    #[test]
+    fn test_identity() {
+        let body = Bytes::from_static(FEED);
+        assert_eq!(decode(None, body.clone(), 100).unwrap(), FEED);
+        assert_eq!(decode(Some("identity"), body, 100).unwrap(), FEED);
+    }
+
+    #[test]
+    fn test_gzip() {
+        let mut gzip = Vec::new();
+        compress(GzEncoder::new(&mut gzip, Compression::default()));
+        assert_eq!(decode(Some("gzip"), gzip.into(), 100).unwrap(), FEED);
+    }
+
+    #[test]
+    fn test_deflate() {
+        let mut zlib = Vec::new();
+        compress(ZlibEncoder::new(&mut zlib, Compression::default()));
+        assert_eq!(decode(Some("deflate"), zlib.into(), 100).unwrap(), FEED);
+
+        let mut raw = Vec::new();
+        compress(DeflateEncoder::new(&mut raw, Compression::default()));
+        assert_eq!(decode(Some("Deflate"), raw.into(), 100).unwrap(), FEED);
+    }
+
+    #[test]
+    fn test_brotli() {
+        let mut br = Vec::new();
+        compress(brotli::CompressorWriter::new(&mut br, 4096, 5, 22));
+        assert_eq!(decode(Some("br"), br.into(), 100).unwrap(), FEED);
+    }
+
+    #[test]
+    fn test_too_large() {
+        let mut gzip = Vec::new();
+        compress(GzEncoder::new(&mut gzip, Compression::default()));
+        let e = decode(Some("gzip"), gzip.into(), 10).unwrap_err();
+        assert!(matches!(e, FetchError::BodyTooLarge(10)));
+    }
+
+    #[test]
+    fn test_unsupported() {
+        let e = decode(Some("zstd"), Bytes::from_static(FEED), 100).unwrap_err();
+        assert_eq!(e.to_string(), "Unsupported Content-Encoding: zstd");
+    }
+
+    #[test]
+    fn test_corrupt() {
+        let e = decode(Some("gzip"), Bytes::from_static(FEED), 100).unwrap_err();
+        assert!(matches!(e, FetchError::Decompress { .. }), "{:?}", e);
+    }
+
+    #[test]
+    fn test_prefix() {
+        assert_eq!(prefix(None, FEED, 10), &FEED[..10]);
+
+        let mut gzip = Vec::new();
+        compress(GzEncoder::new(&mut gzip, Compression::default()));
+        assert_eq!(prefix(Some("gzip"), &gzip, 10), &FEED[..10]);
+
+        // Whatever was decoded before the stream was cut off is kept.
+        let mut gzip = GzEncoder::new(Vec::new(), Compression::default());
+        for i in 0..1000 {
+            write!(gzip, "<p>Error {i}</p>").unwrap();
+        }
+        let gzip = gzip.finish().unwrap();
+        let decoded = prefix(Some("gzip"), &gzip[..gzip.len() / 2], 4096);
+        assert!(decoded.starts_with(b"<p>Error 0</p><p>Error 1</p>"));
+
+        assert!(prefix(Some("zstd"), FEED, 10).is_empty());
+    }
+}
@@ -14,6 +14,16 @@ mod tests;
 
 const TEXT_HTML: MediaType = MediaType::new(TEXT, HTML);
 
+/// Media types of feeds linked from HTML pages.
+const FEED_TYPES: [&str; 3] = [
+    "application/rss+xml",
+    "application/atom+xml",
+    "application/feed+json",
+];
+
+/// Maximum number of words in a title built from an item's text.
+const TITLE_WORDS: usize = 12;
+
 /// Extract a summary, given an item's href and summary/content.
 pub fn summary(
     href: &str,
@@ -47,13 +57,96 @@ pub fn summary(
     Ok(None)
 }
 
+/// Extract the first block of plain text from an item's content.
+pub fn content_text(content: &Content) -> Result<Option<String>, Error> {
+    let Some(body) = &content.body else {
+        return Ok(None);
+    };
+    if content.content_type.essence() == TEXT_HTML {
+        summary_from_html_summary(body)
+    } else {
+        Ok(Some(body.clone()))
+    }
+}
+
+/// Build a title from the first few words of some text.
+pub fn title(text: &str) -> String {
+    let mut words = text.split_whitespace();
+    let mut title = words
+        .by_ref()
+        .take(TITLE_WORDS)
+        .collect::<Vec<_>>()
+        .join(" ");
+    if words.next().is_some() {
+        title.push('…');
+    }
+    title
+}
+
+/// Find the first feed linked from an HTML page's `<link rel="alternate">` tags.
+pub fn feed_link(html: &str) -> Result<Option<String>, Error> {
+    let mut reader = html_reader(html);
+
+    loop {
+        let tag = match reader.read_event() {
+            Ok(Event::Eof) => {
+                break;
+            }
+            Ok(Event::Start(tag) | Event::Empty(tag))
+                if tag.name().as_ref().eq_ignore_ascii_case(b"link") =>
+            {
+                tag
+            }
+            // Feed links must be in the head, so don't bother parsing the rest of the page.
+            Ok(Event::Start(tag)) if tag.name().as_ref().eq_ignore_ascii_case(b"body") => {
+                break;
+            }
+            Ok(_) => {
+                continue;
+            }
+            // Real-world HTML isn't always parseable as XML, e.g. inline scripts containing `<`.
+            Err(e) => {
+                tracing::debug!("stopped looking for feed links: {}", e);
+                break;
+            }
+        };
+
+        let mut is_alternate = false;
+        let mut is_feed = false;
+        let mut href = None;
+        for attr in tag.html_attributes() {
+            let attr = attr?;
+            match attr.key.as_ref().to_ascii_lowercase().as_slice() {
+                b"rel" => {
+                    let rel = attr_value(attr, reader.decoder())?;
+                    is_alternate = rel
+                        .split_ascii_whitespace()
+                        .any(|rel| rel.eq_ignore_ascii_case("alternate"));
+                }
+                b"type" => {
+                    let type_ = attr_value(attr, reader.decoder())?;
+                    is_feed = FEED_TYPES
+                        .iter()
+                        .any(|feed_type| type_.trim().eq_ignore_ascii_case(feed_type));
+                }
+                b"href" => {
+                    href = Some(attr_value(attr, reader.decoder())?.into_owned());
+                }
+                _ => {}
+            }
+        }
+        if is_alternate && is_feed {
+            if let Some(href) = href {
+                return Ok(Some(href));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
 fn summary_from_html_summary(summary: &str) -> Result<Option<String>, Error> {
-    let mut reader = Reader::from_str(summary);
-    reader.config_mut().trim_text(true);
-    // HTML doesn't require self-closing tags to be formatted properly,
-    // e.g. you can do <a><img></a>.
-    reader.config_mut().check_end_names = false;
-    reader.config_mut().allow_unmatched_ends = true;
+    let mut reader = html_reader(summary);
 
     let mut text_content: Option<String> = None;
 
@@ -88,12 +181,7 @@ fn summary_from_html_summary(summary: &str) -> Result<Option<String>, Error> {
 }
 
 fn summary_from_html_body(item_href: &str, body: &str) -> Result<Option<String>, Error> {
-    let mut reader = Reader::from_str(body);
-    reader.config_mut().trim_text(true);
-    // HTML doesn't require self-closing tags to be formatted properly,
-    // e.g. you can do <a><img></a>.
-    reader.config_mut().check_end_names = false;
-    reader.config_mut().allow_unmatched_ends = true;
+    let mut reader = html_reader(body);
 
     loop {
         match reader.read_event()? {
@@ -142,6 +230,17 @@ fn summary_from_html_body(item_href: &str, body: &str) -> Result<Option<String>,
     Ok(None)
 }
 
+/// A reader lenient enough for HTML, rather than XML.
+fn html_reader(html: &str) -> Reader<&[u8]> {
+    let mut reader = Reader::from_str(html);
+    reader.config_mut().trim_text(true);
+    // HTML doesn't require self-closing tags to be formatted properly,
+    // e.g. you can do <a><img></a>.
+    reader.config_mut().check_end_names = false;
+    reader.config_mut().allow_unmatched_ends = true;
+    reader
+}
+
 fn attr_value(attr: Attribute<'_>, decoder: Decoder) -> Result<Cow<'_, str>, Error> {
     // Try to properly decode the value
     if let Ok(value) = attr.decode_and_unescape_value(decoder) {
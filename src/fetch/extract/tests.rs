@@ -109,3 +109,89 @@ fn summary_from_html_body_improperly_escaped_url() {
 
     assert_eq!(summary.unwrap(), Some(String::from("Test title 2")));
 }
+
+#[test]
+fn title_short_text() {
+    assert_eq!(title("  Hello,\n world!  "), "Hello, world!");
+}
+
+#[test]
+fn title_truncate_long_text() {
+    assert_eq!(
+        title("one two three four five six seven eight nine ten eleven twelve thirteen"),
+        "one two three four five six seven eight nine ten eleven twelve…"
+    );
+}
+
+#[test]
+fn feed_link_rss() {
+    let link = feed_link(
+        r#"
+        <!DOCTYPE html>
+        <html>
+            <head>
+                <meta charset="utf-8">
+                <link rel="stylesheet" href="/style.css">
+                <link rel="alternate" type="text/html" hreflang="fr" href="/fr/">
+                <link rel="alternate" type="application/rss+xml" title="RSS" href="/feed.xml">
+                <link rel="alternate" type="application/atom+xml" href="/atom.xml">
+            </head>
+            <body></body>
+        </html>
+    "#,
+    );
+
+    assert_eq!(link.unwrap(), Some(String::from("/feed.xml")));
+}
+
+#[test]
+fn feed_link_json_feed_unquoted() {
+    let link = feed_link(
+        r#"
+        <html>
+            <HEAD>
+                <LINK REL="Alternate Home" TYPE=application/feed+json HREF=https://example.com/feed.json>
+            </HEAD>
+        </html>
+    "#,
+    );
+
+    assert_eq!(
+        link.unwrap(),
+        Some(String::from("https://example.com/feed.json"))
+    );
+}
+
+#[test]
+fn feed_link_none() {
+    let link = feed_link(
+        r#"
+        <html>
+            <head>
+                <link rel="icon" href="/favicon.ico">
+            </head>
+            <body>
+                <link rel="alternate" type="application/rss+xml" href="/not-in-head.xml">
+            </body>
+        </html>
+    "#,
+    );
+
+    assert_eq!(link.unwrap(), None);
+}
+
+#[test]
+fn feed_link_unparseable_rest_of_page() {
+    let link = feed_link(
+        r#"
+        <html>
+            <head>
+                <link rel="alternate" type="application/atom+xml" href="/atom.xml">
+                <script>if (a <b) {}</script>
+            </head>
+        </html>
+    "#,
+    );
+
+    assert_eq!(link.unwrap(), Some(String::from("/atom.xml")));
+}
@@ -0,0 +1,243 @@
+use crate::fetch::{Feed, FetchError, Item};
+use chrono::{DateTime, TimeDelta, Utc};
+use hyper::StatusCode;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Failures in a row after which a feed counts as failing.
+const FAILURE_THRESHOLD: u32 = 3;
+/// How long after its newest item a feed counts as stale.
+const STALE_AFTER: TimeDelta = TimeDelta::days(90);
+/// How long a feed that's no longer fetched stays on the list.
+const FORGET_AFTER: TimeDelta = TimeDelta::days(30);
+
+/// What happened on the way to a fetch's result.
+#[derive(Default)]
+pub struct Attempt {
+    /// Whether a request was made, rather than the feed being served from the cache.
+    pub requested: bool,
+    pub status: Option<StatusCode>,
+    /// Size of the (decoded) body.
+    pub bytes: Option<usize>,
+}
+
+/// How a feed has fared, from every time it was fetched.
+#[derive(Debug, Clone, Default)]
+pub struct FeedHealth {
+    pub last_success: Option<DateTime<Utc>>,
+    pub last_error: Option<(DateTime<Utc>, FetchError)>,
+    pub consecutive_failures: u32,
+    /// How long the last request took, including redirects and the body.
+    pub latency: Option<Duration>,
+    pub status: Option<StatusCode>,
+    pub bytes: Option<usize>,
+    pub item_count: Option<usize>,
+    pub newest_item: Option<DateTime<Utc>>,
+}
+
+impl FeedHealth {
+    pub fn is_failing(&self) -> bool {
+        self.consecutive_failures >= FAILURE_THRESHOLD
+    }
+
+    /// Whether the feed hasn't published anything in a long time.
+    pub fn is_stale(&self, now: DateTime<Utc>) -> bool {
+        self.newest_item
+            .is_some_and(|newest| now - newest > STALE_AFTER)
+    }
+
+    /// When the feed was last fetched, successfully or not.
+    fn last_fetched(&self) -> Option<DateTime<Utc>> {
+        let last_error = self.last_error.as_ref().map(|(at, _)| *at);
+        self.last_success.max(last_error)
+    }
+}
+
+/// Outcomes of fetching feeds, keyed by the URL they were requested from.
+#[derive(Default)]
+pub struct Health {
+    feeds: Mutex<HashMap<String, FeedHealth>>,
+}
+
+impl Health {
+    pub fn record(
+        &self,
+        url: String,
+        latency: Duration,
+        attempt: Attempt,
+        result: &Result<(Feed, Vec<Item>), FetchError>,
+        now: DateTime<Utc>,
+    ) {
+        let mut feeds = self.feeds.lock().unwrap();
+        // Forget feeds that are no longer fetched, e.g. after being removed from a collection.
+        feeds.retain(|_, health| {
+            health
+                .last_fetched()
+                .is_some_and(|last| now - last < FORGET_AFTER)
+        });
+        let health = feeds.entry(url).or_default();
+
+        // Keep the last request's details when served from the cache.
+        if attempt.requested {
+            health.latency = Some(latency);
+            health.status = attempt.status;
+            health.bytes = attempt.bytes;
+        }
+
+        match result {
+            Ok((_, items)) => {
+                health.last_success = Some(now);
+                health.consecutive_failures = 0;
+                health.item_count = Some(items.len());
+                health.newest_item = items.iter().map(|item| item.timestamp).max();
+            }
+            Err(e) => {
+                health.last_error = Some((now, e.clone()));
+                health.consecutive_failures += 1;
+            }
+        }
+    }
+
+    /// Every feed fetched so far, by URL.
+    pub fn feeds(&self) -> Vec<(String, FeedHealth)> {
+        let mut feeds = self
+            .feeds
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(url, health)| (url.clone(), health.clone()))
+            .collect::<Vec<_>>();
+        feeds.sort_by(|(a, _), (b, _)| a.cmp(b));
+        feeds
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const URL: &str = "https://example.com/feed.xml";
+
+    fn example_feed() -> Feed {
+        Feed {
+            url: URL.to_owned(),
+            title: String::from("Example"),
+            ..Default::default()
+        }
+    }
+
+    fn item(timestamp: DateTime<Utc>) -> Item {
+        Item {
+            timestamp,
+            href: String::from("https://example.com/1"),
+            title: String::from("First"),
+            thumbnail_url: None,
+            summary: None,
+        }
+    }
+
+    fn requested(status: StatusCode) -> Attempt {
+        Attempt {
+            requested: true,
+            status: Some(status),
+            bytes: Some(100),
+        }
+    }
+
+    #[test]
+    fn test_failures() {
+        let health = Health::default();
+        let now = Utc::now();
+        let error = Err(FetchError::Timeout(Duration::from_secs(10)));
+
+        for _ in 1..FAILURE_THRESHOLD {
+            health.record(
+                URL.to_owned(),
+                Duration::ZERO,
+                Attempt::default(),
+                &error,
+                now,
+            );
+        }
+        assert!(!health.feeds()[0].1.is_failing());
+
+        health.record(
+            URL.to_owned(),
+            Duration::ZERO,
+            Attempt::default(),
+            &error,
+            now,
+        );
+        let (_, feed) = health.feeds().pop().unwrap();
+        assert!(feed.is_failing());
+        assert!(feed.last_success.is_none());
+
+        // One success resets the count.
+        let ok = Ok((example_feed(), vec![item(now)]));
+        health.record(URL.to_owned(), Duration::ZERO, Attempt::default(), &ok, now);
+        let (_, feed) = health.feeds().pop().unwrap();
+        assert!(!feed.is_failing());
+        assert!(!feed.is_stale(now));
+        assert_eq!(feed.last_success, Some(now));
+        assert!(feed.last_error.is_some());
+    }
+
+    #[test]
+    fn test_stale() {
+        let health = Health::default();
+        let now = Utc::now();
+        let old = now - TimeDelta::days(200);
+        let ok = Ok((
+            example_feed(),
+            vec![item(old), item(old - TimeDelta::days(1))],
+        ));
+        health.record(
+            URL.to_owned(),
+            Duration::from_millis(50),
+            requested(StatusCode::OK),
+            &ok,
+            now,
+        );
+
+        let (url, feed) = health.feeds().pop().unwrap();
+        assert_eq!(url, URL);
+        assert!(feed.is_stale(now));
+        assert_eq!(feed.newest_item, Some(old));
+        assert_eq!(feed.item_count, Some(2));
+        assert_eq!(feed.status, Some(StatusCode::OK));
+        assert_eq!(feed.latency, Some(Duration::from_millis(50)));
+
+        // Served from the cache, the last request's details are kept.
+        health.record(URL.to_owned(), Duration::ZERO, Attempt::default(), &ok, now);
+        let (_, feed) = health.feeds().pop().unwrap();
+        assert_eq!(feed.latency, Some(Duration::from_millis(50)));
+        assert_eq!(feed.bytes, Some(100));
+    }
+
+    #[test]
+    fn test_forget() {
+        let health = Health::default();
+        let now = Utc::now();
+        let ok = Ok((example_feed(), Vec::new()));
+        let then = now - FORGET_AFTER - TimeDelta::days(1);
+        health.record(
+            URL.to_owned(),
+            Duration::ZERO,
+            Attempt::default(),
+            &ok,
+            then,
+        );
+        health.record(
+            String::from("https://example.com/other.xml"),
+            Duration::ZERO,
+            Attempt::default(),
+            &ok,
+            now,
+        );
+
+        let feeds = health.feeds();
+        assert_eq!(feeds.len(), 1);
+        assert_eq!(feeds[0].0, "https://example.com/other.xml");
+    }
+}
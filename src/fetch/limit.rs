@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::time::Instant;
+
+type Hosts = Arc<Mutex<HashMap<String, Arc<Semaphore>>>>;
+
+/// Caps on concurrent requests, overall and to each host.
+pub struct Limits {
+    total: Arc<Semaphore>,
+    per_host: usize,
+    /// Only hosts with requests in flight or waiting, so hosts from visitors' queries don't pile up.
+    hosts: Hosts,
+}
+
+/// Permission to make a request, until dropped.
+pub struct Permit {
+    _total: OwnedSemaphorePermit,
+    _host: HostPermit,
+}
+
+/// A host's share of a [`Permit`], which forgets the host once it's the last one out.
+struct HostPermit {
+    permit: Option<OwnedSemaphorePermit>,
+    semaphore: Arc<Semaphore>,
+    hosts: Hosts,
+    host: String,
+}
+
+impl Drop for HostPermit {
+    fn drop(&mut self) {
+        drop(self.permit.take());
+        let mut hosts = self.hosts.lock().unwrap();
+        // Anyone else requesting from the host holds a reference too, taken under the same lock.
+        if hosts
+            .get(&self.host)
+            .is_some_and(|s| Arc::ptr_eq(s, &self.semaphore))
+            && Arc::strong_count(&self.semaphore) == 2
+        {
+            hosts.remove(&self.host);
+        }
+    }
+}
+
+impl Limits {
+    pub fn new(total: NonZeroUsize, per_host: NonZeroUsize) -> Self {
+        Self {
+            total: Arc::new(Semaphore::new(total.get())),
+            per_host: per_host.get(),
+            hosts: Default::default(),
+        }
+    }
+
+    /// Wait until a request to `host` is allowed.
+    pub async fn acquire(&self, host: &str) -> Permit {
+        let host_semaphore = Arc::clone(
+            self.hosts
+                .lock()
+                .unwrap()
+                .entry(host.to_owned())
+                .or_insert_with(|| Arc::new(Semaphore::new(self.per_host))),
+        );
+
+        let queued = host_semaphore.available_permits() == 0 || self.total.available_permits() == 0;
+        let start = Instant::now();
+
+        // Wait for the host first, so requests queued for a busy host don't hold up other hosts.
+        let host_permit = HostPermit {
+            permit: Some(Arc::clone(&host_semaphore).acquire_owned().await.unwrap()),
+            semaphore: host_semaphore,
+            hosts: Arc::clone(&self.hosts),
+            host: host.to_owned(),
+        };
+        let total_permit = Arc::clone(&self.total).acquire_owned().await.unwrap();
+
+        if queued {
+            tracing::debug!(
+                "queued for {:?} before requesting from {}",
+                start.elapsed(),
+                host
+            );
+        }
+
+        Permit {
+            _total: total_permit,
+            _host: host_permit,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::time;
+
+    fn limits(total: usize, per_host: usize) -> Limits {
+        Limits::new(
+            NonZeroUsize::new(total).unwrap(),
+            NonZeroUsize::new(per_host).unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_per_host() {
+        let limits = limits(10, 2);
+
+        let a1 = limits.acquire("a.example.com").await;
+        let _a2 = limits.acquire("a.example.com").await;
+        let _b1 = limits.acquire("b.example.com").await;
+
+        // A third request to the same host waits...
+        let pending = time::timeout(Duration::from_millis(10), limits.acquire("a.example.com"));
+        assert!(pending.await.is_err());
+
+        // ...until another one finishes.
+        drop(a1);
+        let pending = time::timeout(Duration::from_millis(10), limits.acquire("a.example.com"));
+        assert!(pending.await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_total() {
+        let limits = limits(2, 2);
+
+        let a = limits.acquire("a.example.com").await;
+        let _b = limits.acquire("b.example.com").await;
+
+        let pending = time::timeout(Duration::from_millis(10), limits.acquire("c.example.com"));
+        assert!(pending.await.is_err());
+
+        drop(a);
+        let pending = time::timeout(Duration::from_millis(10), limits.acquire("c.example.com"));
+        assert!(pending.await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_forget_hosts() {
+        let limits = Arc::new(limits(10, 1));
+
+        let a = limits.acquire("a.example.com").await;
+        let waiting = tokio::spawn({
+            let limits = Arc::clone(&limits);
+            async move { limits.acquire("a.example.com").await }
+        });
+        time::sleep(Duration::from_millis(10)).await;
+
+        // The host is still needed by the request waiting for it...
+        drop(a);
+        let b = waiting.await.unwrap();
+        assert!(limits.hosts.lock().unwrap().contains_key("a.example.com"));
+
+        // ...but not once that's done too.
+        drop(b);
+        assert!(limits.hosts.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,228 @@
+use crate::fetch::{parse_url, FetchError};
+use crate::url;
+use hyper::header::LOCATION;
+use hyper::http::uri::Scheme;
+use hyper::{HeaderMap, StatusCode, Uri};
+use std::collections::HashSet;
+
+/// The redirects followed so far while getting one URL.
+pub struct Redirects {
+    max: usize,
+    visited: HashSet<Uri>,
+    all_permanent: bool,
+    /// Where the original URL permanently moved to, if every redirect so far was permanent.
+    pub moved_to: Option<String>,
+}
+
+impl Redirects {
+    pub fn new(max: usize) -> Self {
+        Self {
+            max,
+            visited: HashSet::new(),
+            all_permanent: true,
+            moved_to: None,
+        }
+    }
+
+    /// Decide what to do with a response from `url`: the URL to follow it to, or `None` if it isn't a redirect.
+    pub fn next(
+        &mut self,
+        url: Uri,
+        status: StatusCode,
+        headers: &HeaderMap,
+    ) -> Result<Option<Uri>, FetchError> {
+        if !matches!(
+            status,
+            StatusCode::MOVED_PERMANENTLY
+                | StatusCode::FOUND
+                | StatusCode::SEE_OTHER
+                | StatusCode::TEMPORARY_REDIRECT
+                | StatusCode::PERMANENT_REDIRECT
+        ) {
+            return Ok(None);
+        }
+
+        let location = headers
+            .get(LOCATION)
+            .and_then(|location| location.to_str().ok())
+            .ok_or(FetchError::MissingLocation(status))?;
+        let next = parse_url(&url::resolve(&url.to_string(), location))?;
+
+        if url.scheme() == Some(&Scheme::HTTPS) && next.scheme() != Some(&Scheme::HTTPS) {
+            return Err(FetchError::InsecureRedirect(next));
+        }
+        self.visited.insert(url);
+        if self.visited.contains(&next) {
+            return Err(FetchError::RedirectLoop(next));
+        }
+        if self.visited.len() > self.max {
+            return Err(FetchError::TooManyRedirects(self.max));
+        }
+
+        // Only a chain of permanent redirects means the original URL is obsolete.
+        self.all_permanent &= matches!(
+            status,
+            StatusCode::MOVED_PERMANENTLY | StatusCode::PERMANENT_REDIRECT
+        );
+        if self.all_permanent {
+            self.moved_to = Some(next.to_string());
+        }
+
+        Ok(Some(next))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn location(location: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(LOCATION, location.parse().unwrap());
+        headers
+    }
+
+    fn uri(url: &str) -> Uri {
+        url.parse().unwrap()
+    }
+
+    #[test]
+    fn test_not_redirect() {
+        let mut redirects = Redirects::new(5);
+        let next = redirects.next(
+            uri("https://example.com/feed"),
+            StatusCode::OK,
+            &location("/other"),
+        );
+        assert!(matches!(next, Ok(None)));
+        assert!(redirects.moved_to.is_none());
+    }
+
+    #[test]
+    fn test_relative() {
+        let mut redirects = Redirects::new(5);
+        let next = redirects
+            .next(
+                uri("https://example.com/feeds/rss.xml"),
+                StatusCode::FOUND,
+                &location("../atom.xml"),
+            )
+            .unwrap();
+        assert_eq!(next, Some(uri("https://example.com/atom.xml")));
+    }
+
+    #[test]
+    fn test_missing_location() {
+        let mut redirects = Redirects::new(5);
+        let next = redirects.next(
+            uri("https://example.com/feed"),
+            StatusCode::FOUND,
+            &HeaderMap::new(),
+        );
+        assert!(matches!(
+            next,
+            Err(FetchError::MissingLocation(StatusCode::FOUND))
+        ));
+    }
+
+    #[test]
+    fn test_insecure() {
+        let mut redirects = Redirects::new(5);
+        let next = redirects.next(
+            uri("https://example.com/feed"),
+            StatusCode::MOVED_PERMANENTLY,
+            &location("http://example.com/feed"),
+        );
+        assert!(matches!(next, Err(FetchError::InsecureRedirect(_))));
+
+        // Upgrading is fine.
+        let next = redirects.next(
+            uri("http://example.com/feed"),
+            StatusCode::MOVED_PERMANENTLY,
+            &location("https://example.com/feed"),
+        );
+        assert!(matches!(next, Ok(Some(_))));
+    }
+
+    #[test]
+    fn test_loop() {
+        let mut redirects = Redirects::new(5);
+        let a = uri("https://example.com/a");
+        let b = uri("https://example.com/b");
+        let next = redirects.next(a, StatusCode::FOUND, &location("/b"));
+        assert!(matches!(next, Ok(Some(_))));
+        let next = redirects.next(b, StatusCode::FOUND, &location("/a"));
+        assert!(matches!(next, Err(FetchError::RedirectLoop(url)) if url.path() == "/a"));
+    }
+
+    #[test]
+    fn test_too_many() {
+        let mut redirects = Redirects::new(2);
+        for i in 0..2 {
+            let url = uri(&format!("https://example.com/{i}"));
+            let next = redirects.next(url, StatusCode::FOUND, &location(&format!("/{}", i + 1)));
+            assert!(matches!(next, Ok(Some(_))));
+        }
+        let next = redirects.next(
+            uri("https://example.com/2"),
+            StatusCode::FOUND,
+            &location("/3"),
+        );
+        assert!(matches!(next, Err(FetchError::TooManyRedirects(2))));
+    }
+
+    #[test]
+    fn test_moved_to() {
+        let mut redirects = Redirects::new(5);
+        redirects
+            .next(
+                uri("https://example.com/a"),
+                StatusCode::MOVED_PERMANENTLY,
+                &location("/b"),
+            )
+            .unwrap();
+        redirects
+            .next(
+                uri("https://example.com/b"),
+                StatusCode::PERMANENT_REDIRECT,
+                &location("/c"),
+            )
+            .unwrap();
+        assert_eq!(redirects.moved_to.as_deref(), Some("https://example.com/c"));
+
+        // After a temporary redirect, the original URL still moved to where the permanent ones led.
+        redirects
+            .next(
+                uri("https://example.com/c"),
+                StatusCode::TEMPORARY_REDIRECT,
+                &location("/d"),
+            )
+            .unwrap();
+        redirects
+            .next(
+                uri("https://example.com/d"),
+                StatusCode::MOVED_PERMANENTLY,
+                &location("/e"),
+            )
+            .unwrap();
+        assert_eq!(redirects.moved_to.as_deref(), Some("https://example.com/c"));
+
+        // Starting with a temporary redirect, the original URL never moved.
+        let mut redirects = Redirects::new(5);
+        redirects
+            .next(
+                uri("https://example.com/a"),
+                StatusCode::FOUND,
+                &location("/b"),
+            )
+            .unwrap();
+        redirects
+            .next(
+                uri("https://example.com/b"),
+                StatusCode::MOVED_PERMANENTLY,
+                &location("/c"),
+            )
+            .unwrap();
+        assert!(redirects.moved_to.is_none());
+    }
+}
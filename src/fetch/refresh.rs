@@ -0,0 +1,237 @@
+use chrono::{DateTime, Datelike, DurationRound, TimeDelta, Timelike, Utc, Weekday};
+use hyper::header::CACHE_CONTROL;
+use hyper::HeaderMap;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::time::Duration;
+
+/// Longest a feed's own hints can keep it from being fetched, before skipped hours and days.
+const MAX_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How often a feed asks to be fetched.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Hints {
+    /// Minimum time between fetches, from `<ttl>` or `sy:updatePeriod` and `sy:updateFrequency`.
+    pub interval: Option<Duration>,
+    /// Hours (in UTC) during which the feed shouldn't be fetched, from `<skipHours>`.
+    pub skip_hours: Vec<u32>,
+    /// Days during which the feed shouldn't be fetched, from `<skipDays>`.
+    pub skip_days: Vec<Weekday>,
+}
+
+impl Hints {
+    /// When a feed fetched at `now` should next be fetched, given these hints and the response's `max-age`.
+    pub fn fresh_until(&self, max_age: Option<Duration>, now: DateTime<Utc>) -> DateTime<Utc> {
+        let interval = self.interval.max(max_age).unwrap_or_default();
+        let mut fresh_until = now + TimeDelta::from_std(interval.min(MAX_INTERVAL)).unwrap();
+
+        // Move past skipped hours, but give up after a week in case every hour is skipped.
+        for _ in 0..7 * 24 {
+            if !self.skip_hours.contains(&fresh_until.hour())
+                && !self.skip_days.contains(&fresh_until.weekday())
+            {
+                break;
+            }
+            fresh_until =
+                fresh_until.duration_trunc(TimeDelta::hours(1)).unwrap() + TimeDelta::hours(1);
+        }
+
+        fresh_until
+    }
+}
+
+/// Parse a feed's refresh hints, from RSS `<ttl>`, `<skipHours>` and `<skipDays>`, and the syndication module.
+pub fn hints(rss: &[u8]) -> Hints {
+    let mut reader = Reader::from_reader(rss);
+    reader.config_mut().trim_text(true);
+
+    let mut hints = Hints::default();
+    let mut ttl = None;
+    let mut update_period = None;
+    let mut update_frequency = None;
+
+    let mut path = Vec::new();
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(tag)) => {
+                path.push(tag.local_name().as_ref().to_ascii_lowercase());
+            }
+            Ok(Event::End(_)) => {
+                path.pop();
+            }
+            Ok(Event::Text(text)) => {
+                let Ok(text) = text.decode() else {
+                    continue;
+                };
+                let text = text.trim();
+                match path.as_slice() {
+                    [.., parent, tag] if parent == b"skiphours" && tag == b"hour" => {
+                        // Some feeds count hours from 1 to 24.
+                        if let Ok(hour) = text.parse::<u32>() {
+                            hints.skip_hours.push(hour % 24);
+                        }
+                    }
+                    [.., parent, tag] if parent == b"skipdays" && tag == b"day" => {
+                        if let Ok(day) = text.parse() {
+                            hints.skip_days.push(day);
+                        }
+                    }
+                    [.., tag] if tag == b"ttl" => {
+                        ttl = text.parse::<u64>().ok();
+                    }
+                    [.., tag] if tag == b"updateperiod" => {
+                        update_period = match text {
+                            "hourly" => Some(60 * 60),
+                            "daily" => Some(24 * 60 * 60),
+                            "weekly" => Some(7 * 24 * 60 * 60),
+                            "monthly" => Some(30 * 24 * 60 * 60),
+                            "yearly" => Some(365 * 24 * 60 * 60),
+                            _ => None,
+                        };
+                    }
+                    [.., tag] if tag == b"updatefrequency" => {
+                        update_frequency = text.parse::<u64>().ok().filter(|&f| f > 0);
+                    }
+                    _ => {}
+                }
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            Ok(_) => {}
+        }
+        // Items come after all the channel-level hints.
+        if path
+            .last()
+            .is_some_and(|tag| tag == b"item" || tag == b"entry")
+        {
+            break;
+        }
+        buf.clear();
+    }
+
+    let ttl = ttl.map(|minutes| Duration::from_secs(minutes * 60));
+    let update_period =
+        update_period.map(|secs| Duration::from_secs(secs / update_frequency.unwrap_or(1)));
+    hints.interval = ttl.max(update_period);
+    hints
+}
+
+/// The `max-age` from a response's `Cache-Control` header, unless it also forbids caching.
+pub fn max_age(headers: &HeaderMap) -> Option<Duration> {
+    let cache_control = headers.get(CACHE_CONTROL)?.to_str().ok()?;
+    let mut max_age = None;
+    for directive in cache_control.split(',') {
+        let directive = directive.trim().to_ascii_lowercase();
+        if directive == "no-store" || directive == "no-cache" {
+            return None;
+        }
+        if let Some(secs) = directive.strip_prefix("max-age=") {
+            max_age = secs.trim_matches('"').parse().ok().map(Duration::from_secs);
+        }
+    }
+    max_age
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use hyper::header::HeaderValue;
+
+    #[test]
+    fn test_hints() {
+        let rss = br#"<?xml version="1.0"?>
+            <rss version="2.0" xmlns:sy="http://purl.org/rss/1.0/modules/syndication/">
+                <channel>
+                    <title>Test</title>
+                    <ttl>60</ttl>
+                    <sy:updatePeriod>daily</sy:updatePeriod>
+                    <sy:updateFrequency>12</sy:updateFrequency>
+                    <skipHours><hour>0</hour><hour>24</hour><hour>1</hour></skipHours>
+                    <skipDays><day>Saturday</day><day>Sunday</day></skipDays>
+                    <item><title>Item</title><ttl>1000</ttl></item>
+                </channel>
+            </rss>"#;
+
+        assert_eq!(
+            hints(rss),
+            Hints {
+                interval: Some(Duration::from_secs(2 * 60 * 60)),
+                skip_hours: vec![0, 0, 1],
+                skip_days: vec![Weekday::Sat, Weekday::Sun],
+            }
+        );
+    }
+
+    #[test]
+    fn test_no_hints() {
+        let atom = br#"<?xml version="1.0"?>
+            <feed xmlns="http://www.w3.org/2005/Atom"><title>Test</title></feed>"#;
+        assert_eq!(hints(atom), Hints::default());
+        assert_eq!(
+            hints(br#"{"version": "https://jsonfeed.org/version/1.1"}"#),
+            Hints::default()
+        );
+    }
+
+    #[test]
+    fn test_max_age() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(max_age(&headers), None);
+
+        headers.insert(
+            CACHE_CONTROL,
+            HeaderValue::from_static("public, max-age=600"),
+        );
+        assert_eq!(max_age(&headers), Some(Duration::from_secs(600)));
+
+        headers.insert(
+            CACHE_CONTROL,
+            HeaderValue::from_static("max-age=600, no-cache"),
+        );
+        assert_eq!(max_age(&headers), None);
+    }
+
+    #[test]
+    fn test_fresh_until() {
+        // A Friday.
+        let now = Utc.with_ymd_and_hms(2025, 5, 2, 22, 30, 0).unwrap();
+
+        assert_eq!(Hints::default().fresh_until(None, now), now);
+
+        let hints = Hints {
+            interval: Some(Duration::from_secs(60 * 60)),
+            ..Default::default()
+        };
+        assert_eq!(hints.fresh_until(None, now), now + TimeDelta::hours(1));
+        assert_eq!(
+            hints.fresh_until(Some(Duration::from_secs(2 * 60 * 60)), now),
+            now + TimeDelta::hours(2)
+        );
+
+        let hints = Hints {
+            interval: Some(Duration::from_secs(365 * 24 * 60 * 60)),
+            ..Default::default()
+        };
+        assert_eq!(hints.fresh_until(None, now), now + TimeDelta::days(1));
+
+        let hints = Hints {
+            interval: Some(Duration::from_secs(60 * 60)),
+            skip_hours: vec![23],
+            skip_days: vec![Weekday::Sat, Weekday::Sun],
+        };
+        assert_eq!(
+            hints.fresh_until(None, now),
+            Utc.with_ymd_and_hms(2025, 5, 5, 0, 0, 0).unwrap()
+        );
+
+        let hints = Hints {
+            skip_hours: (0..24).collect(),
+            ..Default::default()
+        };
+        assert_eq!(
+            hints.fresh_until(None, now),
+            now + TimeDelta::days(7) - TimeDelta::minutes(30)
+        );
+    }
+}
@@ -0,0 +1,220 @@
+use crate::err::Error;
+use crate::fetch::FetchError;
+use hyper_util::client::legacy::connect::dns::{GaiResolver, Name};
+use std::collections::HashSet;
+use std::future::Future;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::vec;
+use tower::Service;
+
+/// Which hosts feeds may be fetched from, to keep visitors from probing internal services.
+#[derive(Debug, Default)]
+pub struct Policy {
+    /// Hosts (names or addresses) that may be fetched even though they're internal.
+    pub allowed_hosts: HashSet<String>,
+}
+
+impl Policy {
+    /// Refuse a host if any of its addresses are internal, unless it's allowed.
+    pub fn check(
+        &self,
+        host: &str,
+        addrs: impl IntoIterator<Item = IpAddr>,
+    ) -> Result<(), FetchError> {
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        if self.allowed_hosts.contains(&host.to_ascii_lowercase()) {
+            return Ok(());
+        }
+        match addrs.into_iter().find(|addr| is_internal(*addr)) {
+            Some(addr) => Err(FetchError::Blocked {
+                host: host.to_owned(),
+                addr,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Check a host that's an IP address, which is connected to without being resolved.
+    pub fn check_literal(&self, host: &str) -> Result<(), FetchError> {
+        match host.trim_start_matches('[').trim_end_matches(']').parse() {
+            Ok(addr) => self.check(host, [addr]),
+            Err(_) => Ok(()),
+        }
+    }
+}
+
+/// Whether an address is loopback, private (RFC 1918), link-local, unique local, or otherwise not on the public internet.
+fn is_internal(addr: IpAddr) -> bool {
+    match addr {
+        IpAddr::V4(addr) => is_internal_v4(addr),
+        IpAddr::V6(addr) => match embedded_v4(addr) {
+            Some(addr) => is_internal_v4(addr),
+            None => is_internal_v6(addr),
+        },
+    }
+}
+
+/// The IPv4 address an IPv6 address leads to, for the ways of embedding one that reach it.
+fn embedded_v4(addr: Ipv6Addr) -> Option<Ipv4Addr> {
+    let [a, b, c, d, e, f, g, h] = addr.segments();
+    let v4 = |high: u16, low: u16| Some(Ipv4Addr::from((u32::from(high) << 16) | u32::from(low)));
+    match [a, b, c, d, e, f] {
+        // IPv4-mapped, ::ffff:0:0/96, and the deprecated IPv4-compatible, ::/96 (including `::1`).
+        [0, 0, 0, 0, 0, 0 | 0xffff] => v4(g, h),
+        // NAT64, 64:ff9b::/96.
+        [0x64, 0xff9b, 0, 0, 0, 0] => v4(g, h),
+        // 6to4, 2002::/16, with the address in the next 32 bits.
+        [0x2002, ..] => v4(b, c),
+        _ => None,
+    }
+}
+
+fn is_internal_v4(addr: Ipv4Addr) -> bool {
+    let [a, b, ..] = addr.octets();
+    addr.is_unspecified()
+        || addr.is_loopback()
+        || addr.is_private()
+        // Including the 169.254.169.254 metadata service.
+        || addr.is_link_local()
+        || addr.is_broadcast()
+        // "This network", 0.0.0.0/8.
+        || a == 0
+        // Carrier-grade NAT, 100.64.0.0/10, including some clouds' metadata services.
+        || (a == 100 && (b & 0b1100_0000) == 64)
+}
+
+fn is_internal_v6(addr: Ipv6Addr) -> bool {
+    let first = addr.segments()[0];
+    addr.is_unspecified()
+        || addr.is_loopback()
+        // Unique local, fc00::/7, including the fd00:ec2::254 metadata service.
+        || (first & 0xfe00) == 0xfc00
+        // Link-local, fe80::/10.
+        || (first & 0xffc0) == 0xfe80
+}
+
+/// Resolves host names like [`GaiResolver`], but refuses internal addresses according to a [`Policy`].
+///
+/// Checking at connection time, rather than beforehand, means a host can't resolve to a public address
+/// for the check and an internal one for the connection.
+#[derive(Clone)]
+pub struct Resolver {
+    inner: GaiResolver,
+    policy: Option<Arc<Policy>>,
+}
+
+impl Resolver {
+    pub fn new(policy: Option<Arc<Policy>>) -> Self {
+        Self {
+            inner: GaiResolver::new(),
+            policy,
+        }
+    }
+}
+
+impl Service<Name> for Resolver {
+    type Response = vec::IntoIter<SocketAddr>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, name: Name) -> Self::Future {
+        let host = name.as_str().to_owned();
+        let policy = self.policy.clone();
+        let resolving = self.inner.call(name);
+        Box::pin(async move {
+            let addrs = resolving.await?.collect::<Vec<_>>();
+            if let Some(policy) = policy {
+                policy.check(&host, addrs.iter().map(|addr| addr.ip()))?;
+            }
+            Ok(addrs.into_iter())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_internal() {
+        for addr in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "172.31.255.255",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.100.100.200",
+            "0.0.0.0",
+            "::1",
+            "::",
+            "fd00:ec2::254",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(is_internal(addr.parse().unwrap()), "{}", addr);
+        }
+
+        for addr in [
+            "93.184.216.34",
+            "172.32.0.1",
+            "100.128.0.1",
+            "2606:2800:220:1:248:1893:25c8:1946",
+            "::ffff:93.184.216.34",
+        ] {
+            assert!(!is_internal(addr.parse().unwrap()), "{}", addr);
+        }
+    }
+
+    #[test]
+    fn test_embedded_v4() {
+        for addr in [
+            // NAT64, to 169.254.169.254.
+            "64:ff9b::a9fe:a9fe",
+            // 6to4, from 127.0.0.1 and 10.0.0.1.
+            "2002:7f00:1::",
+            "2002:a00:1::1",
+            // IPv4-compatible.
+            "::127.0.0.1",
+            "::192.168.1.1",
+        ] {
+            assert!(is_internal(addr.parse().unwrap()), "{}", addr);
+        }
+
+        // The same, embedding 93.184.216.34.
+        for addr in ["64:ff9b::5db8:d822", "2002:5db8:d822::1", "::93.184.216.34"] {
+            assert!(!is_internal(addr.parse().unwrap()), "{}", addr);
+        }
+    }
+
+    #[test]
+    fn test_policy() {
+        let policy = Policy {
+            allowed_hosts: HashSet::from([String::from("feeds.internal"), String::from("::1")]),
+        };
+        let loopback = "127.0.0.1".parse().unwrap();
+        let public = "93.184.216.34".parse().unwrap();
+
+        assert!(policy.check("example.com", [public]).is_ok());
+        assert_eq!(
+            policy
+                .check("example.com", [public, loopback])
+                .unwrap_err()
+                .to_string(),
+            "Refusing to fetch example.com: 127.0.0.1 is an internal address"
+        );
+        assert!(policy.check("Feeds.Internal", [loopback]).is_ok());
+
+        assert!(policy.check_literal("127.0.0.1").is_err());
+        assert!(policy.check_literal("[::1]").is_ok());
+        assert!(policy.check_literal("[fe80::1]").is_err());
+        assert!(policy.check_literal("example.com").is_ok());
+    }
+}
@@ -0,0 +1,321 @@
+use super::*;
+
+const URL: &str = "https://example.com/feed.xml";
+
+fn rss(items: &str) -> String {
+    format!(
+        r#"<?xml version="1.0"?>
+        <rss version="2.0">
+            <channel>
+                <title>Test feed</title>
+                <link>https://example.com</link>
+                {items}
+            </channel>
+        </rss>
+    "#
+    )
+}
+
+#[test]
+fn snippet_empty() {
+    assert_eq!(snippet(b""), "");
+}
+
+#[test]
+fn snippet_collapse_whitespace() {
+    assert_eq!(
+        snippet(b"<html>\n  <body>Not   Found</body>\n</html>\n"),
+        "<html> <body>Not Found</body> </html>"
+    );
+}
+
+#[test]
+fn snippet_truncate() {
+    let long = "x".repeat(SNIPPET_LEN + 10);
+    assert_eq!(
+        snippet(long.as_bytes()),
+        format!("{}…", &long[..SNIPPET_LEN])
+    );
+}
+
+#[test]
+fn parse_all_valid() {
+    let rss = rss(r#"
+        <item>
+            <title>First</title>
+            <link>https://example.com/1</link>
+            <pubDate>Wed, 02 May 2025 07:00:00 GMT</pubDate>
+        </item>
+        <item>
+            <title>Second</title>
+            <link>https://example.com/2</link>
+            <pubDate>Thu, 03 May 2025 07:00:00 GMT</pubDate>
+        </item>
+    "#);
+
+    let (feed, items) = parse(
+        URL.to_owned(),
+        None,
+        rss.as_bytes(),
+        &Default::default(),
+        false,
+    )
+    .unwrap();
+
+    assert_eq!(feed.title, "Test feed");
+    assert!(feed.warnings.is_empty());
+    let titles = items.iter().map(|i| i.title.as_str()).collect::<Vec<_>>();
+    assert_eq!(titles, ["First", "Second"]);
+}
+
+#[test]
+fn parse_strict_bad_entry_fails_feed() {
+    let rss = rss(r#"
+        <item>
+            <title>First</title>
+            <link>https://example.com/1</link>
+            <pubDate>Wed, 02 May 2025 07:00:00 GMT</pubDate>
+        </item>
+        <item>
+            <title>No link</title>
+            <pubDate>Thu, 03 May 2025 07:00:00 GMT</pubDate>
+        </item>
+    "#);
+
+    let result = parse(
+        URL.to_owned(),
+        None,
+        rss.as_bytes(),
+        &Default::default(),
+        false,
+    );
+
+    let e = result.unwrap_err();
+    assert_eq!(e.to_string(), "Missing link");
+    assert_eq!(e.kind(), ErrorKind::Validation);
+}
+
+#[test]
+fn parse_lenient_skips_bad_entries() {
+    let rss = rss(r#"
+        <item>
+            <title>First</title>
+            <link>https://example.com/1</link>
+            <pubDate>Wed, 02 May 2025 07:00:00 GMT</pubDate>
+        </item>
+        <item>
+            <guid>no-link</guid>
+            <title>No link</title>
+            <pubDate>Thu, 03 May 2025 07:00:00 GMT</pubDate>
+        </item>
+        <item>
+            <guid>no-title</guid>
+            <link>https://example.com/3</link>
+            <pubDate>Thu, 03 May 2025 07:00:00 GMT</pubDate>
+        </item>
+        <item>
+            <title>Last</title>
+            <link>https://example.com/4</link>
+            <pubDate>Fri, 04 May 2025 07:00:00 GMT</pubDate>
+        </item>
+    "#);
+
+    let (feed, items) = parse(
+        URL.to_owned(),
+        None,
+        rss.as_bytes(),
+        &Default::default(),
+        true,
+    )
+    .unwrap();
+
+    let titles = items.iter().map(|i| i.title.as_str()).collect::<Vec<_>>();
+    assert_eq!(titles, ["First", "Last"]);
+    assert_eq!(
+        feed.warnings,
+        [
+            "Skipped entry no-link: Missing link",
+            "Skipped entry no-title: Missing title",
+        ]
+    );
+}
+
+#[test]
+fn parse_lenient_bad_feed_still_fails() {
+    let result = parse(
+        URL.to_owned(),
+        None,
+        b"<html>not a feed</html>",
+        &Default::default(),
+        true,
+    );
+
+    assert_eq!(result.unwrap_err().kind().code(), "parse");
+}
+
+#[test]
+fn error_retryable() {
+    let status = |status| FetchError::Status {
+        status,
+        snippet: String::new(),
+    };
+    assert!(FetchError::Timeout(Duration::from_secs(10)).is_retryable());
+    assert!(status(StatusCode::BAD_GATEWAY).is_retryable());
+    assert!(!status(StatusCode::NOT_FOUND).is_retryable());
+    // Rate limiting is left to the backoff.
+    assert!(!status(StatusCode::SERVICE_UNAVAILABLE).is_retryable());
+    assert!(!FetchError::BackingOff {
+        host: String::from("example.com"),
+        until: Utc::now(),
+    }
+    .is_retryable());
+    assert!(!FetchError::Parse(String::from("bad")).is_retryable());
+}
+
+#[test]
+fn parse_title_from_summary() {
+    let rss = rss(r#"
+        <item>
+            <description>Just a short post.</description>
+            <link>https://example.com/1</link>
+            <pubDate>Wed, 02 May 2025 07:00:00 GMT</pubDate>
+        </item>
+    "#);
+
+    let (_, items) = parse(
+        URL.to_owned(),
+        None,
+        rss.as_bytes(),
+        &Default::default(),
+        false,
+    )
+    .unwrap();
+
+    assert_eq!(items[0].title, "Just a short post.");
+    assert_eq!(items[0].summary, None);
+}
+
+#[test]
+fn parse_title_from_long_summary() {
+    let rss = rss(r#"
+        <item>
+            <description>This post is long enough that it will not fit entirely into the title.</description>
+            <link>https://example.com/1</link>
+            <pubDate>Wed, 02 May 2025 07:00:00 GMT</pubDate>
+        </item>
+    "#);
+
+    let (_, items) = parse(
+        URL.to_owned(),
+        None,
+        rss.as_bytes(),
+        &Default::default(),
+        false,
+    )
+    .unwrap();
+
+    assert_eq!(
+        items[0].title,
+        "This post is long enough that it will not fit entirely into…"
+    );
+    assert_eq!(
+        items[0].summary.as_deref(),
+        Some("This post is long enough that it will not fit entirely into the title.")
+    );
+}
+
+#[test]
+fn parse_title_from_content() {
+    let rss = r#"<?xml version="1.0"?>
+        <feed xmlns="http://www.w3.org/2005/Atom">
+            <title>Test feed</title>
+            <id>https://example.com/</id>
+            <updated>2025-05-02T07:00:00Z</updated>
+            <entry>
+                <id>https://example.com/1</id>
+                <link href="https://example.com/1"/>
+                <updated>2025-05-02T07:00:00Z</updated>
+                <content type="html">&lt;p&gt;Hello from the content.&lt;/p&gt;&lt;p&gt;More.&lt;/p&gt;</content>
+            </entry>
+        </feed>
+    "#;
+
+    let (_, items) = parse(
+        URL.to_owned(),
+        None,
+        rss.as_bytes(),
+        &Default::default(),
+        false,
+    )
+    .unwrap();
+
+    assert_eq!(items[0].title, "Hello from the content.");
+}
+
+#[test]
+fn parse_link_from_id() {
+    let rss = rss(r#"
+        <item>
+            <guid>https://example.com/1</guid>
+            <title>First</title>
+            <pubDate>Wed, 02 May 2025 07:00:00 GMT</pubDate>
+        </item>
+    "#);
+
+    let (_, items) = parse(
+        URL.to_owned(),
+        None,
+        rss.as_bytes(),
+        &Default::default(),
+        false,
+    )
+    .unwrap();
+
+    assert_eq!(items[0].href, "https://example.com/1");
+}
+
+#[test]
+fn parse_timestamp_from_feed_updated() {
+    let rss = r#"<?xml version="1.0"?>
+        <feed xmlns="http://www.w3.org/2005/Atom">
+            <title>Test feed</title>
+            <id>https://example.com/</id>
+            <updated>2025-05-02T07:00:00Z</updated>
+            <entry>
+                <id>https://example.com/1</id>
+                <title>First</title>
+                <link href="https://example.com/1"/>
+            </entry>
+        </feed>
+    "#;
+
+    let (_, items) = parse(
+        URL.to_owned(),
+        None,
+        rss.as_bytes(),
+        &Default::default(),
+        false,
+    )
+    .unwrap();
+
+    assert_eq!(
+        items[0].timestamp,
+        "2025-05-02T07:00:00Z".parse::<DateTime<Utc>>().unwrap()
+    );
+}
+
+#[test]
+fn parse_timestamp_first_seen() {
+    let rss = rss(r#"
+        <item>
+            <title>First</title>
+            <link>https://example.com/1</link>
+        </item>
+    "#);
+    let first_seen = cache::FirstSeen::default();
+
+    let (_, first) = parse(URL.to_owned(), None, rss.as_bytes(), &first_seen, false).unwrap();
+    let (_, second) = parse(URL.to_owned(), None, rss.as_bytes(), &first_seen, false).unwrap();
+
+    assert_eq!(first[0].timestamp, second[0].timestamp);
+}
@@ -1,14 +1,19 @@
 #![allow(clippy::enum_variant_names)]
 
 use std::io;
+use std::sync::Arc;
+use std::time::Duration;
 use tracing_subscriber::filter::LevelFilter;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
 
+mod config;
 mod err;
 mod fetch;
+mod metrics;
 mod opt;
 mod server;
+mod store;
 mod url;
 
 #[tokio::main(flavor = "multi_thread")]
@@ -16,6 +21,20 @@ async fn main() -> Result<(), io::Error> {
     let opt::Options {
         verbose,
         listen_addr,
+        config,
+        store,
+        poll_interval,
+        max_redirects,
+        connect_timeout,
+        read_timeout,
+        page_timeout,
+        max_body_size,
+        max_connections,
+        max_connections_per_host,
+        block_internal,
+        allowed_hosts,
+        strict,
+        metrics,
     } = clap::Parser::parse();
 
     tracing_subscriber::registry()
@@ -27,7 +46,41 @@ async fn main() -> Result<(), io::Error> {
         .with(tracing_subscriber::fmt::layer())
         .init();
 
-    server::run(listen_addr).await?;
+    let config = match config {
+        Some(path) => config::load(&path)?,
+        None => Default::default(),
+    };
+    let refresh = config.refresh();
+    let hosts = config.hosts();
+    let store = store.map(store::Store::load).transpose()?;
+
+    server::run(
+        listen_addr,
+        config,
+        store,
+        Duration::from_secs(poll_interval),
+        Duration::from_secs(connect_timeout),
+        Duration::from_secs(page_timeout),
+        fetch::Config {
+            max_redirects,
+            read_timeout: Duration::from_secs(read_timeout),
+            lenient: !strict,
+            refresh,
+            max_body_size,
+            max_connections,
+            max_connections_per_host,
+            policy: block_internal.then(|| {
+                Arc::new(fetch::Policy {
+                    allowed_hosts: allowed_hosts
+                        .iter()
+                        .map(|host| host.to_ascii_lowercase())
+                        .collect(),
+                })
+            }),
+            metrics: metrics.then(|| Arc::new(metrics::Metrics::new(hosts))),
+        },
+    )
+    .await?;
 
     Ok(())
 }
@@ -0,0 +1,222 @@
+use crate::fetch::ErrorKind;
+use hyper::{Method, StatusCode};
+use prometheus_client::encoding::text::encode;
+use prometheus_client::encoding::EncodeLabelSet;
+use prometheus_client::metrics::counter::Counter;
+use prometheus_client::metrics::family::Family;
+use prometheus_client::metrics::histogram::{exponential_buckets, Histogram};
+use prometheus_client::registry::Registry;
+use std::collections::HashSet;
+use std::time::Duration;
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct RequestLabels {
+    method: String,
+    route: String,
+    status: u16,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct RouteLabels {
+    route: String,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct HostLabels {
+    host: String,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct KindLabels {
+    kind: &'static str,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct CacheLabels {
+    result: &'static str,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct FormatLabels {
+    format: &'static str,
+}
+
+type Histograms<L> = Family<L, Histogram, fn() -> Histogram>;
+
+/// Counters and histograms for Prometheus to scrape from `/metrics`.
+pub struct Metrics {
+    registry: Registry,
+    requests: Family<RequestLabels, Counter>,
+    request_duration: Histograms<RouteLabels>,
+    fetch_duration: Histograms<HostLabels>,
+    fetch_errors: Family<KindLabels, Counter>,
+    downloaded: Counter,
+    cache: Family<CacheLabels, Counter>,
+    items_rendered: Family<FormatLabels, Counter>,
+    /// Hosts to label fetches with, so arbitrary feed URLs in queries can't grow the labels without bound.
+    hosts: HashSet<String>,
+}
+
+impl Metrics {
+    /// Metrics labeling fetches with these hosts (e.g. of configured feeds), and any others as `other`.
+    pub fn new(hosts: HashSet<String>) -> Self {
+        let mut metrics = Self {
+            registry: Registry::with_prefix(env!("CARGO_PKG_NAME")),
+            requests: Default::default(),
+            // From 5ms to about 20s, since pages wait for their feeds.
+            request_duration: Family::new_with_constructor(|| {
+                Histogram::new(exponential_buckets(0.005, 2.0, 13))
+            }),
+            fetch_duration: Family::new_with_constructor(|| {
+                Histogram::new(exponential_buckets(0.01, 2.0, 12))
+            }),
+            fetch_errors: Default::default(),
+            downloaded: Default::default(),
+            cache: Default::default(),
+            items_rendered: Default::default(),
+            hosts,
+        };
+
+        let registry = &mut metrics.registry;
+        registry.register(
+            "http_requests",
+            "Requests served, by route and status",
+            metrics.requests.clone(),
+        );
+        registry.register(
+            "http_request_duration_seconds",
+            "Time taken to serve requests, by route",
+            metrics.request_duration.clone(),
+        );
+        registry.register(
+            "fetch_duration_seconds",
+            "Time taken to fetch feeds, including redirects and the body, by host of configured feeds (others as other)",
+            metrics.fetch_duration.clone(),
+        );
+        registry.register(
+            "fetch_errors",
+            "Failed feed fetches, by kind of error",
+            metrics.fetch_errors.clone(),
+        );
+        registry.register(
+            "fetch_downloaded_bytes",
+            "Bytes of response bodies downloaded, before decompression",
+            metrics.downloaded.clone(),
+        );
+        registry.register(
+            "fetch_cache",
+            "Feed loads served from the cache (including after a 304) or downloaded",
+            metrics.cache.clone(),
+        );
+        registry.register(
+            "items_rendered",
+            "Items rendered, by output format",
+            metrics.items_rendered.clone(),
+        );
+
+        metrics
+    }
+
+    /// Record a served request, by its route pattern (e.g. `/c/{name}`) rather than its path, to bound the labels.
+    pub fn request(&self, method: &Method, route: &str, status: StatusCode, duration: Duration) {
+        self.requests
+            .get_or_create(&RequestLabels {
+                method: method.to_string(),
+                route: route.to_owned(),
+                status: status.as_u16(),
+            })
+            .inc();
+        self.request_duration
+            .get_or_create(&RouteLabels {
+                route: route.to_owned(),
+            })
+            .observe(duration.as_secs_f64());
+    }
+
+    pub fn fetch(&self, host: &str, duration: Duration) {
+        let host = if self.hosts.contains(host) {
+            host
+        } else {
+            "other"
+        };
+        self.fetch_duration
+            .get_or_create(&HostLabels {
+                host: host.to_owned(),
+            })
+            .observe(duration.as_secs_f64());
+    }
+
+    pub fn fetch_error(&self, kind: ErrorKind) {
+        self.fetch_errors
+            .get_or_create(&KindLabels { kind: kind.code() })
+            .inc();
+    }
+
+    pub fn downloaded(&self, bytes: usize) {
+        self.downloaded.inc_by(bytes as u64);
+    }
+
+    /// Record whether a feed was served from the cache, or had to be downloaded.
+    pub fn cache(&self, hit: bool) {
+        let result = if hit { "hit" } else { "miss" };
+        self.cache.get_or_create(&CacheLabels { result }).inc();
+    }
+
+    pub fn rendered(&self, format: &'static str, items: usize) {
+        self.items_rendered
+            .get_or_create(&FormatLabels { format })
+            .inc_by(items as u64);
+    }
+
+    /// Everything recorded so far, in the OpenMetrics text format.
+    pub fn encode(&self) -> String {
+        let mut text = String::new();
+        encode(&mut text, &self.registry).unwrap();
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        let metrics = Metrics::new(HashSet::from([String::from("example.com")]));
+        metrics.request(
+            &Method::GET,
+            "/c/{name}",
+            StatusCode::OK,
+            Duration::from_millis(20),
+        );
+        metrics.fetch("example.com", Duration::from_millis(300));
+        metrics.fetch("example.net", Duration::from_millis(300));
+        metrics.fetch_error(ErrorKind::Dns);
+        metrics.downloaded(1000);
+        metrics.downloaded(500);
+        metrics.cache(true);
+        metrics.cache(false);
+        metrics.cache(false);
+        metrics.rendered("atom", 30);
+
+        let text = metrics.encode();
+        for line in [
+            r#"katamari_http_requests_total{method="GET",route="/c/{name}",status="200"} 1"#,
+            r#"katamari_http_request_duration_seconds_count{route="/c/{name}"} 1"#,
+            r#"katamari_fetch_duration_seconds_bucket{le="0.32",host="example.com"} 1"#,
+            r#"katamari_fetch_duration_seconds_bucket{le="0.32",host="other"} 1"#,
+            r#"katamari_fetch_errors_total{kind="dns"} 1"#,
+            r#"katamari_fetch_downloaded_bytes_total 1500"#,
+            r#"katamari_fetch_cache_total{result="hit"} 1"#,
+            r#"katamari_fetch_cache_total{result="miss"} 2"#,
+            r#"katamari_items_rendered_total{format="atom"} 30"#,
+        ] {
+            assert!(
+                text.lines().any(|l| l == line),
+                "{} not in:\n{}",
+                line,
+                text
+            );
+        }
+    }
+}
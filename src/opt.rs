@@ -1,5 +1,7 @@
 use clap::{ArgAction, Parser};
 use std::net::SocketAddr;
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
 #[clap(version, about)]
@@ -9,4 +11,60 @@ pub struct Options {
     pub verbose: u8,
 
     pub listen_addr: SocketAddr,
+
+    /// TOML config file defining named feed collections
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+
+    /// File to keep every item from configured feeds in, polling them in the background
+    #[arg(long)]
+    pub store: Option<PathBuf>,
+
+    /// Seconds between background polls of configured feeds, with --store
+    #[arg(long, default_value_t = 900)]
+    pub poll_interval: u64,
+
+    /// Maximum number of redirects to follow when fetching a feed
+    #[arg(long, default_value_t = 10)]
+    pub max_redirects: usize,
+
+    /// Seconds to wait for a connection to a feed's host
+    #[arg(long, default_value_t = 10)]
+    pub connect_timeout: u64,
+
+    /// Seconds to wait for a feed's response headers or body
+    #[arg(long, default_value_t = 20)]
+    pub read_timeout: u64,
+
+    /// Seconds to wait for all feeds on a page, after which the finished ones are shown
+    #[arg(long, default_value_t = 30)]
+    pub page_timeout: u64,
+
+    /// Maximum size in bytes of a feed's response, before and after decompression
+    #[arg(long, default_value_t = 10 * 1024 * 1024)]
+    pub max_body_size: usize,
+
+    /// Maximum number of feed requests in flight at once
+    #[arg(long, default_value_t = NonZeroUsize::new(32).unwrap())]
+    pub max_connections: NonZeroUsize,
+
+    /// Maximum number of feed requests in flight to any one host
+    #[arg(long, default_value_t = NonZeroUsize::new(4).unwrap())]
+    pub max_connections_per_host: NonZeroUsize,
+
+    /// Refuse to fetch feeds from loopback, private, link-local and other internal addresses
+    #[arg(long)]
+    pub block_internal: bool,
+
+    /// Host to fetch from even though it's internal, with --block-internal (can be repeated)
+    #[arg(long = "allow-host", value_name = "HOST")]
+    pub allowed_hosts: Vec<String>,
+
+    /// Fail the whole feed if any entry is invalid, instead of skipping that entry
+    #[arg(long)]
+    pub strict: bool,
+
+    /// Serve Prometheus metrics at /metrics
+    #[arg(long)]
+    pub metrics: bool,
 }
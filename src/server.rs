@@ -1,46 +1,102 @@
-use crate::fetch::FetchClient;
+use crate::config::Config;
+use crate::fetch::{self, Fetcher};
+use crate::metrics::Metrics;
+use crate::store::Store;
+use axum::middleware;
 use axum::routing::get;
 use axum::Router;
 use hyper_rustls::HttpsConnector;
+use hyper_util::client::legacy::connect::HttpConnector;
 use hyper_util::client::legacy::Client;
 use hyper_util::rt::TokioExecutor;
 use std::io;
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 use tower::ServiceBuilder;
 use tower_http::compression::CompressionLayer;
 use tower_http::trace::TraceLayer;
 
+mod aggregate;
+mod atom;
+mod health;
+mod html;
+mod json;
+mod opml;
+mod poll;
+mod query;
 mod routes;
 
 struct AppState {
-    client: FetchClient,
+    config: Config,
+    fetcher: Fetcher,
+    /// If set, configured feeds are polled in the background and served from here.
+    store: Option<Store>,
+    page_timeout: Duration,
+    /// If set, served at `/metrics`.
+    metrics: Option<Arc<Metrics>>,
 }
 
-pub async fn run(addr: SocketAddr) -> Result<(), io::Error> {
+pub async fn run(
+    addr: SocketAddr,
+    config: Config,
+    store: Option<Store>,
+    poll_interval: Duration,
+    connect_timeout: Duration,
+    page_timeout: Duration,
+    fetch_config: fetch::Config,
+) -> Result<(), io::Error> {
     let listener = tokio::net::TcpListener::bind(addr).await?;
 
     tracing::info!("listening on {}", addr);
 
+    let mut http =
+        HttpConnector::new_with_resolver(fetch::Resolver::new(fetch_config.policy.clone()));
+    http.enforce_http(false);
+    http.set_connect_timeout(Some(connect_timeout));
+
     let client = Client::builder(TokioExecutor::new()).build(
         HttpsConnector::<()>::builder()
             .with_native_roots()?
             .https_or_http()
             .enable_http1()
             .enable_http2()
-            .build(),
+            .wrap_connector(http),
     );
 
-    let state = Arc::new(AppState { client });
+    let metrics = fetch_config.metrics.clone();
+    let state = Arc::new(AppState {
+        config,
+        fetcher: Fetcher::new(client, fetch_config),
+        store,
+        page_timeout,
+        metrics: metrics.clone(),
+    });
+
+    tokio::spawn(poll::run(Arc::clone(&state), poll_interval));
 
-    let app = Router::new()
+    let mut app = Router::new()
         .route("/", get(routes::index))
-        .with_state(state)
-        .layer(
-            ServiceBuilder::new()
-                .layer(TraceLayer::new_for_http())
-                .layer(CompressionLayer::new().br(true)),
-        );
+        .route("/atom", get(routes::atom))
+        .route("/json", get(routes::json))
+        .route("/opml", get(routes::opml).post(routes::opml_upload))
+        .route("/opml/export", get(routes::opml_export))
+        .route("/c/", get(routes::collections))
+        .route("/c/{name}", get(routes::collection))
+        .route("/c/{name}/atom", get(routes::collection_atom))
+        .route("/c/{name}/json", get(routes::collection_json))
+        .route("/c/{name}/opml", get(routes::collection_opml))
+        .route("/health", get(routes::health))
+        .route("/metrics", get(routes::metrics));
+    if let Some(metrics) = metrics {
+        app = app.route_layer(middleware::from_fn_with_state(metrics, routes::track));
+    }
+
+    let app = app.with_state(state).layer(
+        ServiceBuilder::new()
+            .layer(TraceLayer::new_for_http())
+            .layer(CompressionLayer::new().br(true)),
+    );
 
     axum::serve(listener, app).await?;
 
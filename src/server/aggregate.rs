@@ -0,0 +1,241 @@
+use crate::err::Error;
+use crate::fetch::{self, Feed, FetchError, Item};
+use crate::server::{query, AppState};
+use crate::url;
+use chrono::{NaiveDate, TimeDelta, Utc};
+use hyper::Uri;
+use std::cmp;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::task::JoinSet;
+use tokio::time::{self, Instant};
+
+/// Feed URLs to load, each with an optional label overriding its title.
+pub type Feeds = Vec<(Uri, Option<String>)>;
+
+/// The combined contents of a set of feeds, ready to be rendered.
+#[derive(Default)]
+pub struct Aggregate {
+    /// Feeds that loaded successfully, by the URL they were requested from.
+    pub feeds: Vec<(Uri, Arc<Feed>)>,
+    pub feed_errors: Vec<(Uri, FetchError)>,
+    pub feed_warnings: Vec<(Uri, Vec<String>)>,
+    pub moved_feeds: Vec<(Uri, String)>,
+    pub discovered_feeds: Vec<(Uri, String)>,
+    pub days: Vec<Day>,
+}
+
+pub struct Day {
+    pub date: NaiveDate,
+    pub items: Vec<ItemsWithFeed>,
+}
+
+pub struct ItemsWithFeed {
+    pub feed: Arc<Feed>,
+    pub item: Item,
+    pub count: usize,
+    pub highlighted: bool,
+}
+
+impl ItemsWithFeed {
+    /// An id for the item as shown on `day`, since the same link can show up on several days.
+    pub fn id(&self, day: NaiveDate) -> String {
+        format!("{}#{}", url::prefix(&self.item.href), day)
+    }
+}
+
+/// Fetch feeds and combine their items.
+pub async fn aggregate(
+    state: &Arc<AppState>,
+    feeds: Feeds,
+    options: &query::Options,
+) -> Result<Aggregate, Error> {
+    let mut groups = aggregate_groups(state, vec![(String::new(), feeds)], options).await?;
+    Ok(groups.pop().unwrap().1)
+}
+
+/// Like [`aggregate`], but for several named groups of feeds, which are fetched together but combined separately.
+pub async fn aggregate_groups(
+    state: &Arc<AppState>,
+    groups: Vec<(String, Feeds)>,
+    options: &query::Options,
+) -> Result<Vec<(String, Aggregate)>, Error> {
+    let deadline = Instant::now() + state.page_timeout;
+
+    // Start all the requests concurrently...
+    let mut pending_feeds = JoinSet::new();
+    let mut pending_urls = HashMap::new();
+    let mut results = Vec::new();
+    for (group, (name, feeds)) in groups.into_iter().enumerate() {
+        results.push((name, Aggregate::default(), Vec::new()));
+        for (url, label) in feeds {
+            let state = Arc::clone(state);
+            let task = pending_feeds.spawn({
+                let url = url.clone();
+                async move {
+                    // Polled feeds are served from the store, without waiting on the network.
+                    let stored = state.store.as_ref().and_then(|s| s.get(&url.to_string()));
+                    let (mut feed, items) = match stored {
+                        Some(stored) => stored,
+                        None => fetch::rss(&state.fetcher, url).await?,
+                    };
+                    if let Some(label) = label {
+                        feed.title = label;
+                    }
+                    Ok((feed, items))
+                }
+            });
+            pending_urls.insert(task.id(), (group, url));
+        }
+    }
+
+    // ...and wait for them to finish, or until the deadline.
+    while let Ok(Some(result)) = time::timeout_at(deadline, pending_feeds.join_next_with_id()).await
+    {
+        let (id, result) = result?;
+        let (group, url) = pending_urls.remove(&id).unwrap();
+        let (_, aggregate, all_feeds) = &mut results[group];
+        match result {
+            Err(e) => {
+                aggregate.feed_errors.push((url, e));
+            }
+            Ok((mut feed, items)) => {
+                if !feed.warnings.is_empty() {
+                    let warnings = std::mem::take(&mut feed.warnings);
+                    aggregate.feed_warnings.push((url.clone(), warnings));
+                }
+                if let Some(moved_to) = &feed.moved_to {
+                    aggregate.moved_feeds.push((url.clone(), moved_to.clone()));
+                }
+                if let Some(discovered) = &feed.discovered {
+                    aggregate
+                        .discovered_feeds
+                        .push((url.clone(), discovered.clone()));
+                }
+                let feed = Arc::new(feed);
+                aggregate.feeds.push((url, Arc::clone(&feed)));
+                all_feeds.push((feed, items));
+            }
+        }
+    }
+
+    // Anything still pending missed the deadline; render without it.
+    pending_feeds.abort_all();
+    for (_, (group, url)) in pending_urls {
+        tracing::debug!("feed timed out: {}", url);
+        let error = FetchError::Timeout(state.page_timeout);
+        results[group].1.feed_errors.push((url, error));
+    }
+
+    Ok(results
+        .into_iter()
+        .map(|(name, mut aggregate, all_feeds)| {
+            aggregate.days = combine(all_feeds, options);
+            (name, aggregate)
+        })
+        .collect())
+}
+
+/// Sort, filter, deduplicate and group items from several feeds into days.
+fn combine(mut all_feeds: Vec<(Arc<Feed>, Vec<Item>)>, options: &query::Options) -> Vec<Day> {
+    // Collect all items into one vec, sorted by date.
+    let mut all_items = Vec::new();
+    for (feed, items) in &mut all_feeds {
+        // Carry along a reference to each item's feed.
+        all_items.extend(items.drain(..).map(|item| (Arc::clone(feed), item)));
+    }
+    all_items.sort_by_key(|(_, item)| cmp::Reverse(item.timestamp));
+
+    // Drop items older than the requested number of days.
+    if let Some(days) = options.days {
+        let cutoff = Utc::now() - TimeDelta::days(days.into());
+        all_items.retain(|(_, item)| item.timestamp >= cutoff);
+    }
+
+    // Drop items that point to a prefix of the feed URL.
+    all_items.retain(|(feed, item)| {
+        let drop = feed.url.starts_with(url::prefix(&item.href));
+        if drop {
+            // This happens for some unimportant GitHub events, e.g. branch deletion,
+            // which just point to the GitHub homepage.
+            tracing::debug!("dropping item: {:#?}", item);
+        }
+        !drop
+    });
+
+    // Split items into one vec per day, and deduplicate them.
+    let mut days = Vec::<Day>::new();
+
+    {
+        let mut url_prefix_to_index = HashMap::<String, usize>::new();
+        let mut item_count = 0;
+        for (feed, item) in all_items {
+            let at_limit = options.limit.is_some_and(|limit| item_count >= limit);
+            // Check whether we need to start a new day.
+            let date = options.date(item.timestamp);
+            if days.last().map(|d| d.date) != Some(date) {
+                if at_limit {
+                    break;
+                }
+                days.push(Day {
+                    date,
+                    items: Default::default(),
+                });
+                url_prefix_to_index.clear();
+            }
+            // Get or insert this item.
+            let day = days.last_mut().unwrap();
+            // Strip hash from the URL, so multiple links to the same page (but e.g. for different events with different anchors) are deduplicated.
+            let url_prefix = url::prefix(&item.href);
+            match url_prefix_to_index.entry(url_prefix.to_owned()) {
+                // If we've already seen this item, increment its count,
+                // and override the entry (so the oldest entry is used).
+                Entry::Occupied(entry) => {
+                    let index = *entry.get();
+                    day.items[index].item = item;
+                    day.items[index].count += 1;
+                }
+                // Otherwise, add a new item, unless we've already shown enough.
+                Entry::Vacant(_) if at_limit => {}
+                Entry::Vacant(entry) => {
+                    item_count += 1;
+                    let index = day.items.len();
+                    day.items.push(ItemsWithFeed {
+                        feed,
+                        item,
+                        count: 1,
+                        highlighted: false,
+                    });
+                    entry.insert(index);
+                }
+            }
+        }
+    }
+
+    // Highlight unique domains for the day.
+    if options.highlight {
+        let mut domain_to_entry_count = HashMap::<String, usize>::new();
+        for day in &mut days {
+            domain_to_entry_count.clear();
+
+            // Collect counts by domain.
+            for i in &mut day.items {
+                let domain = url::domain(&i.item.href);
+                *domain_to_entry_count.entry(domain.to_owned()).or_default() += 1;
+            }
+
+            let max_count = domain_to_entry_count.values().max().copied().unwrap_or(0);
+
+            // Mark the items with the lowest count as highlighted.
+            for i in &mut day.items {
+                let domain = url::domain(&i.item.href);
+                let entry_count = domain_to_entry_count[domain];
+                // Highlight if there are fewer than 3 entries with this domain, and there are less entries than the most common domain.
+                i.highlighted = entry_count < 3 && entry_count < max_count;
+            }
+        }
+    }
+
+    days
+}
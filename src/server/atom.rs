@@ -0,0 +1,144 @@
+use crate::server::aggregate::Aggregate;
+use chrono::{SecondsFormat, Utc};
+use quick_xml::escape::escape;
+
+/// Render aggregated items as an Atom 1.0 feed, served from `self_url`.
+pub fn render(aggregate: &Aggregate, self_url: &str) -> String {
+    let items = aggregate
+        .days
+        .iter()
+        .flat_map(|day| day.items.iter().map(move |i| (day.date, i)));
+
+    let updated = items
+        .clone()
+        .map(|(_, i)| i.item.timestamp)
+        .max()
+        .unwrap_or_else(Utc::now);
+
+    let mut xml = format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+<id>{}</id>
+<title>katamari</title>
+<link rel="self" href="{}"/>
+<updated>{}</updated>
+<author><name>katamari</name></author>
+<generator version="{}">katamari</generator>
+"#,
+        escape(self_url),
+        escape(self_url),
+        updated.to_rfc3339_opts(SecondsFormat::Secs, true),
+        env!("CARGO_PKG_VERSION"),
+    );
+
+    for (day, i) in items {
+        let item_count = if i.count > 1 {
+            format!(" ({}x)", i.count)
+        } else {
+            String::new()
+        };
+        let summary = if let Some(summary) = &i.item.summary {
+            format!("<summary>{}</summary>\n", escape(summary))
+        } else {
+            String::new()
+        };
+        xml.push_str(&format!(
+            r#"<entry>
+<id>{}</id>
+<title>{}{}</title>
+<link href="{}"/>
+<updated>{}</updated>
+<source><id>{}</id><title>{}</title><link href="{}"/></source>
+{}</entry>
+"#,
+            escape(i.id(day)),
+            escape(&i.item.title),
+            item_count,
+            escape(&i.item.href),
+            i.item.timestamp.to_rfc3339_opts(SecondsFormat::Secs, true),
+            escape(&i.feed.url),
+            escape(&i.feed.title),
+            escape(&i.feed.url),
+            summary,
+        ));
+    }
+
+    xml.push_str("</feed>\n");
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetch::{Feed, Item};
+    use crate::server::aggregate::{Day, ItemsWithFeed};
+    use chrono::NaiveDate;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_render() {
+        let feed = Arc::new(Feed {
+            url: String::from("https://example.com/feed.xml"),
+            title: String::from("Example <feed>"),
+            ..Default::default()
+        });
+        let item = |href: &str, title: &str, summary: Option<&str>, count| ItemsWithFeed {
+            feed: Arc::clone(&feed),
+            item: Item {
+                timestamp: "2025-05-02T07:00:00Z".parse().unwrap(),
+                href: href.to_owned(),
+                title: title.to_owned(),
+                thumbnail_url: None,
+                summary: summary.map(str::to_owned),
+            },
+            count,
+            highlighted: false,
+        };
+        let aggregate = Aggregate {
+            days: vec![
+                Day {
+                    date: NaiveDate::from_ymd_opt(2025, 5, 2).unwrap(),
+                    items: vec![
+                        item("https://example.com/1?a&b", "First & best", Some("<p>"), 1),
+                        item("https://example.com/2", "Second", None, 3),
+                    ],
+                },
+                // The same link again, a day earlier.
+                Day {
+                    date: NaiveDate::from_ymd_opt(2025, 5, 1).unwrap(),
+                    items: vec![item("https://example.com/2", "Second", None, 1)],
+                },
+            ],
+            ..Default::default()
+        };
+
+        let xml = render(
+            &aggregate,
+            "http://localhost:3000/atom?https://example.com/feed.xml",
+        );
+
+        assert!(xml.contains(
+            "<source><id>https://example.com/feed.xml</id><title>Example &lt;feed&gt;</title>"
+        ));
+
+        let parsed = feed_rs::parser::parse(xml.as_bytes()).unwrap();
+        assert_eq!(parsed.title.unwrap().content, "katamari");
+        assert_eq!(parsed.entries.len(), 3);
+        assert_eq!(
+            parsed.entries[0].title.as_ref().unwrap().content,
+            "First & best"
+        );
+        assert_eq!(parsed.entries[0].links[0].href, "https://example.com/1?a&b");
+        assert_eq!(parsed.entries[0].summary.as_ref().unwrap().content, "<p>");
+        assert_eq!(
+            parsed.entries[1].title.as_ref().unwrap().content,
+            "Second (3x)"
+        );
+        assert!(parsed.entries[1].summary.is_none());
+
+        assert_eq!(parsed.entries[0].id, "https://example.com/1?a&b#2025-05-02");
+        assert_eq!(parsed.entries[1].id, "https://example.com/2#2025-05-02");
+        assert_eq!(parsed.entries[2].id, "https://example.com/2#2025-05-01");
+        assert_eq!(parsed.entries[2].links[0].href, "https://example.com/2");
+    }
+}
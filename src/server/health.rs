@@ -0,0 +1,120 @@
+use crate::fetch::FeedHealth;
+use crate::server::html::{Text, Url};
+use chrono::{DateTime, Utc};
+use std::fmt::Write;
+
+/// Render how each fetched feed has fared, with failing and stale feeds first.
+pub fn render(mut feeds: Vec<(String, FeedHealth)>, now: DateTime<Utc>) -> String {
+    feeds.sort_by_key(|(_, health)| (!health.is_failing(), !health.is_stale(now)));
+
+    let mut html = String::from(
+        r#"<!DOCTYPE html>
+        <html>
+            <head><meta charset="utf-8"><title>katamari health</title></head>
+            <body>
+                <h1>Feed health</h1>
+        "#,
+    );
+    if feeds.is_empty() {
+        html.push_str("<p>No feeds fetched yet.</p>");
+    } else {
+        html.push_str(
+            "<table><tr><th>Feed</th><th>Flags</th><th>Last success</th><th>Last error</th><th>Latency</th><th>HTTP</th><th>Bytes</th><th>Items</th><th>Newest item</th></tr>",
+        );
+    }
+
+    for (url, health) in &feeds {
+        let mut flags = Vec::new();
+        if health.is_failing() {
+            flags.push(format!(
+                "failing ({} in a row)",
+                health.consecutive_failures
+            ));
+        }
+        if health.is_stale(now) {
+            flags.push(String::from("stale"));
+        }
+        let last_error = match &health.last_error {
+            Some((at, e)) => format!("{}: {}", time(Some(*at)), e),
+            None => String::new(),
+        };
+
+        write!(
+            html,
+            r#"<tr><td><a href="{}">{}</a></td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>"#,
+            Url(url),
+            Text(url),
+            flags.join(", "),
+            time(health.last_success),
+            Text(&last_error),
+            health
+                .latency
+                .map(|latency| format!("{} ms", latency.as_millis()))
+                .unwrap_or_default(),
+            health
+                .status
+                .map(|status| status.as_u16().to_string())
+                .unwrap_or_default(),
+            health.bytes.map(|b| b.to_string()).unwrap_or_default(),
+            health.item_count.map(|c| c.to_string()).unwrap_or_default(),
+            time(health.newest_item),
+        )
+        .unwrap();
+    }
+
+    if !feeds.is_empty() {
+        html.push_str("</table>");
+    }
+    html.push_str(r#"<p><a href="/">Usage</a></p></body></html>"#);
+    html
+}
+
+fn time(time: Option<DateTime<Utc>>) -> String {
+    time.map(|time| time.format("%Y-%m-%d %H:%M UTC").to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetch::FetchError;
+    use chrono::TimeDelta;
+    use std::time::Duration;
+
+    #[test]
+    fn test_render() {
+        let now = Utc::now();
+        let healthy = FeedHealth {
+            last_success: Some(now),
+            newest_item: Some(now),
+            ..Default::default()
+        };
+        let failing = FeedHealth {
+            last_error: Some((now, FetchError::Parse(String::from("<bad>")))),
+            consecutive_failures: 5,
+            latency: Some(Duration::from_millis(120)),
+            ..Default::default()
+        };
+        let stale = FeedHealth {
+            newest_item: Some(now - TimeDelta::days(365)),
+            ..Default::default()
+        };
+
+        let html = render(
+            vec![
+                (String::from("https://a.example.com/"), healthy),
+                (String::from("https://b.example.com/"), failing),
+                (String::from("https://c.example.com/"), stale),
+            ],
+            now,
+        );
+
+        let position = |s| html.find(s).unwrap();
+        assert!(position("https://b.example.com/") < position("https://c.example.com/"));
+        assert!(position("https://c.example.com/") < position("https://a.example.com/"));
+        assert!(html.contains("failing (5 in a row)"));
+        assert!(html.contains("<td>stale</td>"));
+        assert!(html.contains("Invalid feed: &lt;bad&gt;"));
+        assert!(html.contains("120 ms"));
+    }
+}
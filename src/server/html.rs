@@ -0,0 +1,73 @@
+use crate::url;
+use quick_xml::escape::{escape, partial_escape};
+use std::fmt::{self, Display};
+
+/// A string written as element text content.
+pub struct Text<'a>(pub &'a str);
+
+impl Display for Text<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&partial_escape(self.0))
+    }
+}
+
+/// A string written inside a double-quoted attribute value.
+pub struct Attr<'a>(pub &'a str);
+
+impl Display for Attr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&escape(self.0))
+    }
+}
+
+/// A URL written inside a double-quoted `href` or `src` attribute.
+///
+/// Only http(s) URLs are allowed, so feeds can't smuggle in e.g. `javascript:` links.
+pub struct Url<'a>(pub &'a str);
+
+impl Display for Url<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if url::is_absolute(self.0) {
+            Attr(self.0).fmt(f)
+        } else {
+            f.write_str("#")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text() {
+        assert_eq!(Text("plain").to_string(), "plain");
+        assert_eq!(
+            Text("<b>Tom & \"Jerry\"</b>").to_string(),
+            "&lt;b&gt;Tom &amp; \"Jerry\"&lt;/b&gt;"
+        );
+    }
+
+    #[test]
+    fn test_attr() {
+        assert_eq!(
+            Attr(r#"" onmouseover="alert(1)"#).to_string(),
+            "&quot; onmouseover=&quot;alert(1)"
+        );
+        assert_eq!(Attr("it's <here>").to_string(), "it&apos;s &lt;here&gt;");
+    }
+
+    #[test]
+    fn test_url() {
+        assert_eq!(
+            Url("https://example.com/?a=1&b=2").to_string(),
+            "https://example.com/?a=1&amp;b=2"
+        );
+        assert_eq!(
+            Url(r#"https://example.com/"><script>"#).to_string(),
+            "https://example.com/&quot;&gt;&lt;script&gt;"
+        );
+        assert_eq!(Url("javascript:alert(1)").to_string(), "#");
+        assert_eq!(Url("data:text/html,<h1>hi</h1>").to_string(), "#");
+    }
+}
@@ -0,0 +1,252 @@
+use crate::server::aggregate::Aggregate;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+
+/// A [JSON Feed 1.1](https://www.jsonfeed.org/version/1.1/) document.
+///
+/// Everything katamari computes beyond the standard fields is under `_katamari` extension objects:
+///
+/// - On the feed: `errors` (`[{ "url", "error" }]`) for feeds that failed to load,
+///   `warnings` (`[{ "url", "warnings": [..] }]`) for feeds with skipped or repaired entries,
+///   `moved` (`[{ "url", "moved_to" }]`) for feeds that were permanently redirected,
+///   and `discovered` (`[{ "url", "feed_url" }]`) for web pages whose linked feed was used instead.
+/// - On each item: `day` (`YYYY-MM-DD`, in the requested timezone) that it was grouped into,
+///   `count` of duplicate items merged into it, whether it's `highlighted`,
+///   and the `source` feed's `title` and `url`.
+#[derive(Serialize)]
+struct JsonFeed<'a> {
+    version: &'static str,
+    title: &'static str,
+    feed_url: &'a str,
+    items: Vec<JsonItem<'a>>,
+    _katamari: FeedExtension<'a>,
+}
+
+#[derive(Serialize)]
+struct FeedExtension<'a> {
+    errors: Vec<FeedError>,
+    warnings: Vec<FeedWarnings<'a>>,
+    moved: Vec<MovedFeed<'a>>,
+    discovered: Vec<DiscoveredFeed<'a>>,
+}
+
+#[derive(Serialize)]
+struct FeedError {
+    url: String,
+    /// What kind of error it was, e.g. `dns` or `http_status`, for clients to act on.
+    code: &'static str,
+    error: String,
+}
+
+#[derive(Serialize)]
+struct FeedWarnings<'a> {
+    url: String,
+    warnings: &'a [String],
+}
+
+#[derive(Serialize)]
+struct MovedFeed<'a> {
+    url: String,
+    moved_to: &'a str,
+}
+
+#[derive(Serialize)]
+struct DiscoveredFeed<'a> {
+    url: String,
+    feed_url: &'a str,
+}
+
+#[derive(Serialize)]
+struct JsonItem<'a> {
+    id: String,
+    url: &'a str,
+    title: &'a str,
+    /// Required (or `content_html`) by the spec: the summary, or the title if there isn't one.
+    content_text: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    summary: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    image: Option<&'a str>,
+    date_published: DateTime<Utc>,
+    _katamari: ItemExtension<'a>,
+}
+
+#[derive(Serialize)]
+struct ItemExtension<'a> {
+    day: NaiveDate,
+    count: usize,
+    highlighted: bool,
+    source: Source<'a>,
+}
+
+#[derive(Serialize)]
+struct Source<'a> {
+    title: &'a str,
+    url: &'a str,
+}
+
+/// Render aggregated items as a JSON Feed, served from `self_url`.
+pub fn render(aggregate: &Aggregate, self_url: &str) -> String {
+    let feed = JsonFeed {
+        version: "https://jsonfeed.org/version/1.1",
+        title: "katamari",
+        feed_url: self_url,
+        items: aggregate
+            .days
+            .iter()
+            .flat_map(|day| day.items.iter().map(move |i| (day.date, i)))
+            .map(|(day, i)| JsonItem {
+                id: i.id(day),
+                url: &i.item.href,
+                title: &i.item.title,
+                content_text: i.item.summary.as_deref().unwrap_or(&i.item.title),
+                summary: i.item.summary.as_deref(),
+                image: i.item.thumbnail_url.as_deref(),
+                date_published: i.item.timestamp,
+                _katamari: ItemExtension {
+                    day,
+                    count: i.count,
+                    highlighted: i.highlighted,
+                    source: Source {
+                        title: &i.feed.title,
+                        url: &i.feed.url,
+                    },
+                },
+            })
+            .collect(),
+        _katamari: FeedExtension {
+            errors: aggregate
+                .feed_errors
+                .iter()
+                .map(|(url, e)| FeedError {
+                    url: url.to_string(),
+                    code: e.kind().code(),
+                    error: e.to_string(),
+                })
+                .collect(),
+            warnings: aggregate
+                .feed_warnings
+                .iter()
+                .map(|(url, warnings)| FeedWarnings {
+                    url: url.to_string(),
+                    warnings,
+                })
+                .collect(),
+            moved: aggregate
+                .moved_feeds
+                .iter()
+                .map(|(url, moved_to)| MovedFeed {
+                    url: url.to_string(),
+                    moved_to,
+                })
+                .collect(),
+            discovered: aggregate
+                .discovered_feeds
+                .iter()
+                .map(|(url, feed_url)| DiscoveredFeed {
+                    url: url.to_string(),
+                    feed_url,
+                })
+                .collect(),
+        },
+    };
+
+    serde_json::to_string(&feed).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetch::{Feed, FetchError, Item};
+    use crate::server::aggregate::{Day, ItemsWithFeed};
+    use hyper::{StatusCode, Uri};
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_render() {
+        let feed = Arc::new(Feed {
+            url: String::from("https://example.com/feed.xml"),
+            title: String::from("Example"),
+            ..Default::default()
+        });
+        let aggregate = Aggregate {
+            feed_errors: vec![(
+                Uri::from_static("https://example.net/feed.xml"),
+                FetchError::Status {
+                    status: StatusCode::NOT_FOUND,
+                    snippet: String::new(),
+                },
+            )],
+            feed_warnings: vec![(
+                Uri::from_static("https://example.com/feed.xml"),
+                vec![String::from("Skipped entry #2: Missing link")],
+            )],
+            moved_feeds: vec![(
+                Uri::from_static("https://example.org/old.xml"),
+                String::from("https://example.org/new.xml"),
+            )],
+            days: vec![Day {
+                date: NaiveDate::from_ymd_opt(2025, 5, 2).unwrap(),
+                items: vec![ItemsWithFeed {
+                    feed: Arc::clone(&feed),
+                    item: Item {
+                        timestamp: "2025-05-02T07:00:00Z".parse().unwrap(),
+                        href: String::from("https://example.com/1"),
+                        title: String::from("First"),
+                        thumbnail_url: None,
+                        summary: Some(String::from("Summary")),
+                    },
+                    count: 2,
+                    highlighted: true,
+                }],
+            }],
+            ..Default::default()
+        };
+
+        let json: Value =
+            serde_json::from_str(&render(&aggregate, "http://localhost:3000/json?x")).unwrap();
+
+        assert_eq!(
+            json,
+            json!({
+                "version": "https://jsonfeed.org/version/1.1",
+                "title": "katamari",
+                "feed_url": "http://localhost:3000/json?x",
+                "items": [{
+                    "id": "https://example.com/1#2025-05-02",
+                    "url": "https://example.com/1",
+                    "title": "First",
+                    "content_text": "Summary",
+                    "summary": "Summary",
+                    "date_published": "2025-05-02T07:00:00Z",
+                    "_katamari": {
+                        "day": "2025-05-02",
+                        "count": 2,
+                        "highlighted": true,
+                        "source": {
+                            "title": "Example",
+                            "url": "https://example.com/feed.xml",
+                        },
+                    },
+                }],
+                "_katamari": {
+                    "errors": [{
+                        "url": "https://example.net/feed.xml",
+                        "code": "http_status",
+                        "error": "HTTP 404 Not Found",
+                    }],
+                    "warnings": [{
+                        "url": "https://example.com/feed.xml",
+                        "warnings": ["Skipped entry #2: Missing link"],
+                    }],
+                    "moved": [{
+                        "url": "https://example.org/old.xml",
+                        "moved_to": "https://example.org/new.xml",
+                    }],
+                    "discovered": [],
+                },
+            })
+        );
+    }
+}
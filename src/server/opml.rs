@@ -0,0 +1,194 @@
+use crate::err::Error;
+use crate::fetch::Feed;
+use quick_xml::escape::escape;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+/// A feed listed in an OPML document.
+#[derive(Debug, PartialEq)]
+pub struct Outline {
+    /// The folders containing this feed, joined with " / ", or empty if it's at the top level.
+    pub group: String,
+    pub url: String,
+    pub title: Option<String>,
+}
+
+/// Parse the feeds out of an OPML document, keeping track of the folders they're nested in.
+pub fn parse(opml: &[u8]) -> Result<Vec<Outline>, Error> {
+    let mut reader = Reader::from_reader(opml);
+    reader.config_mut().trim_text(true);
+
+    let mut buf = Vec::new();
+    let mut folders = Vec::<Option<String>>::new();
+    let mut outlines = Vec::new();
+
+    loop {
+        match reader.read_event_into(&mut buf)? {
+            Event::Eof => {
+                break;
+            }
+            Event::Start(tag) if tag.name().as_ref() == b"outline" => {
+                let (url, title) = attributes(&tag, &reader)?;
+                match url {
+                    // A feed, which is unusual but allowed to have children.
+                    Some(url) => {
+                        outlines.push(outline(&folders, url, title));
+                        folders.push(None);
+                    }
+                    // A folder.
+                    None => folders.push(Some(title.unwrap_or_default())),
+                }
+            }
+            Event::Empty(tag) if tag.name().as_ref() == b"outline" => {
+                if let (Some(url), title) = attributes(&tag, &reader)? {
+                    outlines.push(outline(&folders, url, title));
+                }
+            }
+            Event::End(tag) if tag.name().as_ref() == b"outline" => {
+                folders.pop();
+            }
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    Ok(outlines)
+}
+
+fn outline(folders: &[Option<String>], url: String, title: Option<String>) -> Outline {
+    let group = folders
+        .iter()
+        .flatten()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join(" / ");
+    Outline { group, url, title }
+}
+
+/// Get the `xmlUrl`, and the `title` (or `text`) of an outline.
+fn attributes(
+    tag: &BytesStart<'_>,
+    reader: &Reader<&[u8]>,
+) -> Result<(Option<String>, Option<String>), Error> {
+    let mut url = None;
+    let mut title = None;
+    let mut text = None;
+    for attr in tag.attributes() {
+        let attr = attr?;
+        let value = attr
+            .decode_and_unescape_value(reader.decoder())?
+            .into_owned();
+        match attr.key.as_ref() {
+            b"xmlUrl" => url = Some(value),
+            b"title" => title = Some(value),
+            b"text" => text = Some(value),
+            _ => {}
+        }
+    }
+    Ok((url, title.or(text)))
+}
+
+/// Render a list of feeds as an OPML document, titled with each feed's own title if it loaded.
+pub fn render(feeds: &[(String, Option<&Feed>)]) -> String {
+    let mut xml = String::from(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<opml version="2.0">
+<head><title>katamari</title></head>
+<body>
+"#,
+    );
+    for (url, feed) in feeds {
+        let title = feed.map(|f| f.title.as_str()).unwrap_or(url);
+        xml.push_str(&format!(
+            r#"<outline type="rss" text="{}" title="{}" xmlUrl="{}"/>
+"#,
+            escape(title),
+            escape(title),
+            escape(url),
+        ));
+    }
+    xml.push_str("</body>\n</opml>\n");
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_nested() {
+        let outlines = parse(
+            br#"<?xml version="1.0"?>
+            <opml version="2.0">
+                <head><title>Subscriptions</title></head>
+                <body>
+                    <outline text="Top level" type="rss" xmlUrl="https://example.com/top.xml"/>
+                    <outline text="Tech">
+                        <outline text="Rust &amp; friends">
+                            <outline title="Rust blog" text="ignored" type="rss" xmlUrl="https://blog.rust-lang.org/feed.xml"/>
+                        </outline>
+                        <outline type="rss" xmlUrl="https://example.com/tech.xml?a=1&amp;b=2"></outline>
+                    </outline>
+                    <outline text="Empty"/>
+                </body>
+            </opml>
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            outlines,
+            [
+                Outline {
+                    group: String::new(),
+                    url: String::from("https://example.com/top.xml"),
+                    title: Some(String::from("Top level")),
+                },
+                Outline {
+                    group: String::from("Tech / Rust & friends"),
+                    url: String::from("https://blog.rust-lang.org/feed.xml"),
+                    title: Some(String::from("Rust blog")),
+                },
+                Outline {
+                    group: String::from("Tech"),
+                    url: String::from("https://example.com/tech.xml?a=1&b=2"),
+                    title: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(parse(b"<opml><body><outline xmlUrl=\"unterminated></body></opml>").is_err());
+    }
+
+    #[test]
+    fn test_render_roundtrip() {
+        let feed = Feed {
+            url: String::from("https://example.com/feed.xml?a=1&b=2"),
+            title: String::from("Example \"feed\""),
+            ..Default::default()
+        };
+        let opml = render(&[
+            (feed.url.clone(), Some(&feed)),
+            (String::from("https://example.net/broken.xml"), None),
+        ]);
+
+        assert_eq!(
+            parse(opml.as_bytes()).unwrap(),
+            [
+                Outline {
+                    group: String::new(),
+                    url: String::from("https://example.com/feed.xml?a=1&b=2"),
+                    title: Some(String::from("Example \"feed\"")),
+                },
+                Outline {
+                    group: String::new(),
+                    url: String::from("https://example.net/broken.xml"),
+                    title: Some(String::from("https://example.net/broken.xml")),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,88 @@
+use crate::fetch;
+use crate::server::AppState;
+use crate::store::{self, Store};
+use hyper::Uri;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinSet;
+use tokio::time::{self, MissedTickBehavior};
+
+/// How long to wait before retrying a feed that failed for a reason that might pass.
+const RETRY_DELAY: Duration = Duration::from_secs(10);
+
+/// Poll every configured feed on an interval, saving their items to the store.
+pub async fn run(state: Arc<AppState>, interval: Duration) {
+    let Some(store) = &state.store else {
+        return;
+    };
+
+    let urls = state
+        .config
+        .collections
+        .values()
+        .flat_map(|collection| &collection.feeds)
+        .filter_map(|feed| feed.url().parse::<Uri>().ok())
+        .collect::<HashSet<_>>();
+    if urls.is_empty() {
+        tracing::warn!("no feeds configured, so nothing to poll");
+        return;
+    }
+
+    let mut interval = time::interval(interval);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        poll(&state, store, &urls).await;
+
+        let path = store.path().to_owned();
+        let json = store.to_json();
+        match tokio::task::spawn_blocking(move || store::write(&path, &json)).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => tracing::error!("failed to save store: {}", e),
+            Err(e) => tracing::error!("failed to save store: {}", e),
+        }
+    }
+}
+
+async fn poll(state: &Arc<AppState>, store: &Store, urls: &HashSet<Uri>) {
+    tracing::info!("polling {} feeds", urls.len());
+
+    let mut pending_feeds = JoinSet::new();
+    for url in urls {
+        let state = Arc::clone(state);
+        let url = url.clone();
+        pending_feeds.spawn(async move {
+            let result = match fetch::rss(&state.fetcher, url.clone()).await {
+                // Give a network hiccup one more chance before recording the failure.
+                Err(e) if e.is_retryable() => {
+                    tracing::debug!("retrying {} after {}", url, e);
+                    time::sleep(RETRY_DELAY).await;
+                    fetch::rss(&state.fetcher, url.clone()).await
+                }
+                result => result,
+            };
+            (url, result)
+        });
+    }
+
+    while let Some(result) = pending_feeds.join_next().await {
+        let (url, result) = match result {
+            Ok(result) => result,
+            Err(e) => {
+                tracing::error!("poll task failed: {}", e);
+                continue;
+            }
+        };
+        match result {
+            Ok((feed, items)) => {
+                tracing::debug!("polled {} items from {}", items.len(), url);
+                store.insert(url.to_string(), feed, items);
+            }
+            Err(e) => {
+                tracing::warn!("failed to poll {}: {}", url, e);
+                store.insert_error(&url.to_string(), e.to_string());
+            }
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use crate::err::Error;
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use chrono_tz::Tz;
+use hyper::Uri;
+use percent_encoding::percent_decode_str;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+enum QueryError {
+    #[error("invalid value for {key}: {value:?}")]
+    InvalidOption { key: &'static str, value: String },
+}
+
+/// Options controlling how the page is built, provided as reserved query params.
+#[derive(Debug, PartialEq)]
+pub struct Options {
+    /// `days=N`: only show items from the last N days.
+    pub days: Option<u32>,
+    /// `limit=N`: show at most N items.
+    pub limit: Option<usize>,
+    /// `tz=Area/Location`: group items into days in this timezone, instead of the server's.
+    pub tz: Option<Tz>,
+    /// `highlight=off`: don't highlight items from uncommon domains.
+    pub highlight: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            days: None,
+            limit: None,
+            tz: None,
+            highlight: true,
+        }
+    }
+}
+
+impl Options {
+    /// The date of a timestamp, in the selected timezone.
+    pub fn date(&self, timestamp: DateTime<Utc>) -> NaiveDate {
+        match self.tz {
+            Some(tz) => timestamp.with_timezone(&tz).date_naive(),
+            None => timestamp.with_timezone(&Local).date_naive(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Query {
+    pub urls: Vec<Uri>,
+    pub options: Options,
+}
+
+/// Parse the feed URLs and options out of a query string.
+///
+/// Each `&`-separated component is either an option (see [`Options`]),
+/// `url=` followed by a percent-encoded URL (required if the URL has its own query string),
+/// or a bare URL.
+pub fn parse(query: &str) -> Result<Query, Error> {
+    let mut urls = Vec::new();
+    let mut options = Options::default();
+
+    for component in query.split('&').filter(|c| !c.is_empty()) {
+        let (key, value) = component.split_once('=').unwrap_or((component, ""));
+        match key {
+            "url" => {
+                let url = percent_decode_str(value).decode_utf8()?;
+                urls.push(url.parse()?);
+            }
+            "days" => options.days = Some(option("days", value, value.parse().ok())?),
+            "limit" => options.limit = Some(option("limit", value, value.parse().ok())?),
+            "tz" => {
+                let value = percent_decode_str(value).decode_utf8()?;
+                options.tz = Some(option("tz", &value, value.parse().ok())?);
+            }
+            "highlight" => {
+                let highlight = match value {
+                    "on" => Some(true),
+                    "off" => Some(false),
+                    _ => None,
+                };
+                options.highlight = option("highlight", value, highlight)?;
+            }
+            _ => urls.push(component.parse()?),
+        }
+    }
+
+    Ok(Query { urls, options })
+}
+
+fn option<T>(key: &'static str, value: &str, parsed: Option<T>) -> Result<T, QueryError> {
+    parsed.ok_or_else(|| QueryError::InvalidOption {
+        key,
+        value: value.to_owned(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn urls(query: &str) -> Vec<String> {
+        parse(query)
+            .unwrap()
+            .urls
+            .iter()
+            .map(|url| url.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_urls_bare() {
+        assert_eq!(
+            urls("https://example.com/feed.xml&https://example.net/atom"),
+            ["https://example.com/feed.xml", "https://example.net/atom"]
+        );
+        assert_eq!(
+            urls("https://example.com/feed?format=rss"),
+            ["https://example.com/feed?format=rss"]
+        );
+    }
+
+    #[test]
+    fn test_urls_encoded() {
+        assert_eq!(
+            urls("url=https%3A%2F%2Fexample.com%2Ffeed%3Fformat%3Drss%26tag%3Drust&https://example.net/atom"),
+            [
+                "https://example.com/feed?format=rss&tag=rust",
+                "https://example.net/atom"
+            ]
+        );
+        assert_eq!(
+            urls("url=https://example.com/feed%3Fa%3D1%26b%3D2"),
+            ["https://example.com/feed?a=1&b=2"]
+        );
+    }
+
+    #[test]
+    fn test_urls_empty_components() {
+        assert_eq!(
+            urls("&https://example.com/feed.xml&"),
+            ["https://example.com/feed.xml"]
+        );
+    }
+
+    #[test]
+    fn test_urls_invalid() {
+        assert!(parse("url=%FF").is_err());
+        assert!(parse("not a url").is_err());
+    }
+
+    #[test]
+    fn test_options() {
+        let query =
+            parse("days=7&https://example.com/feed.xml&limit=200&tz=Europe%2FParis&highlight=off")
+                .unwrap();
+        assert_eq!(query.urls.len(), 1);
+        assert_eq!(
+            query.options,
+            Options {
+                days: Some(7),
+                limit: Some(200),
+                tz: Some(chrono_tz::Europe::Paris),
+                highlight: false,
+            }
+        );
+
+        let query = parse("tz=America/New_York&highlight=on").unwrap();
+        assert_eq!(query.options.tz, Some(chrono_tz::America::New_York));
+        assert!(query.options.highlight);
+    }
+
+    #[test]
+    fn test_options_invalid() {
+        assert_eq!(
+            parse("days=seven").unwrap_err().to_string(),
+            r#"invalid value for days: "seven""#
+        );
+        assert!(parse("limit=-1").is_err());
+        assert!(parse("tz=Mars/Olympus_Mons").is_err());
+        assert!(parse("highlight=maybe").is_err());
+    }
+
+    #[test]
+    fn test_options_date() {
+        let timestamp = "2025-05-02T23:30:00Z".parse::<DateTime<Utc>>().unwrap();
+        let options = Options {
+            tz: Some(chrono_tz::Europe::Paris),
+            ..Default::default()
+        };
+        assert_eq!(
+            options.date(timestamp),
+            NaiveDate::from_ymd_opt(2025, 5, 3).unwrap()
+        );
+    }
+}
@@ -1,158 +1,466 @@
-use crate::err::ResponseError;
-use crate::fetch::{self, Feed, Item};
-use crate::server::AppState;
-use crate::url;
-use axum::extract::{RawQuery, State};
-use axum::response::{Html, IntoResponse};
+use crate::err::{Error, ResponseError};
+use crate::fetch::{self, FetchError};
+use crate::metrics::Metrics;
+use crate::server::aggregate::{aggregate, aggregate_groups, Aggregate, Feeds};
+use crate::server::html::{Attr, Text, Url};
+use crate::server::{atom, health, json, opml, query, AppState};
+use axum::body::Bytes;
+use axum::extract::{MatchedPath, Path, RawQuery, Request, State};
+use axum::middleware::Next;
+use axum::response::{Html, IntoResponse, Response};
 use base64::prelude::BASE64_URL_SAFE;
 use base64::Engine;
-use chrono::{Local, NaiveDate};
-use hyper::Uri;
-use std::cmp;
-use std::collections::hash_map::Entry;
-use std::collections::HashMap;
+use chrono::Utc;
+use hyper::header::{CONTENT_TYPE, HOST};
+use hyper::{HeaderMap, StatusCode, Uri};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use std::collections::BTreeMap;
 use std::sync::Arc;
 use thiserror::Error;
-use tokio::task::JoinSet;
+use tokio::time::Instant;
+
+#[cfg(test)]
+mod tests;
 
 #[derive(Debug, Error)]
 enum GetError {
     #[error("no URLs provided in query string")]
     NoUrls,
+    #[error("URLs are not allowed in the query string when uploading OPML")]
+    UnexpectedUrls,
+    #[error("no collection named {0:?}")]
+    NoSuchCollection(String),
 }
 
 /// Load a list of RSS feeds, provided as query params, and display the results in chronological order.
 ///
 /// e.g. `http://localhost:3000/?https://www.rust-lang.org/feeds/releases.xml&https://blog.rust-lang.org/feed.xml`
+///
+/// Feed URLs with their own query string must be percent-encoded in a `url=` param,
+/// e.g. `http://localhost:3000/?url=https%3A%2F%2Fexample.com%2Ffeed%3Fformat%3Drss%26tag%3Drust`
+///
+/// Options (`days`, `limit`, `tz`, `highlight`) may be mixed in with the URLs; see [`query::Options`].
+///
+/// Without a query string, shows how to use it instead.
 pub async fn index(
     State(state): State<Arc<AppState>>,
     RawQuery(params): RawQuery,
+) -> Result<Response, ResponseError> {
+    if params.as_deref().unwrap_or_default().is_empty() {
+        return Ok(landing(&state).into_response());
+    }
+    let (feeds, options) = query_feeds(params)?;
+
+    let aggregate = aggregate(&state, feeds, &options).await?;
+    rendered(&state, "html", [&aggregate]);
+    Ok((status([&aggregate]), Html(render(aggregate))).into_response())
+}
+
+/// Like [`index`], but as an Atom feed.
+pub async fn atom(
+    State(state): State<Arc<AppState>>,
+    uri: Uri,
+    headers: HeaderMap,
+    RawQuery(params): RawQuery,
 ) -> Result<impl IntoResponse, ResponseError> {
-    let Some(params) = params else {
-        return Err(GetError::NoUrls.into());
-    };
+    let (feeds, options) = query_feeds(params)?;
 
-    // Start all the requests concurrently...
-    let mut pending_feeds = JoinSet::new();
-    for url in params.split('&') {
-        let url: Uri = url.parse()?;
-        let feed = fetch::rss(state.client.clone(), url.clone());
-        pending_feeds.spawn(async { (url, feed.await) });
-    }
-
-    // ...and wait for them to finish.
-    let mut feed_errors = Vec::new();
-    let mut all_feeds = Vec::new();
-    while let Some(result) = pending_feeds.join_next().await {
-        let (url, result) = result?;
-        match result {
-            Err(e) => {
-                feed_errors.push((url, e));
-            }
-            Ok((feed, items)) => {
-                all_feeds.push((feed, items));
-            }
-        }
+    let aggregate = aggregate(&state, feeds, &options).await?;
+    rendered(&state, "atom", [&aggregate]);
+    Ok((
+        status([&aggregate]),
+        [(CONTENT_TYPE, "application/atom+xml; charset=utf-8")],
+        atom::render(&aggregate, &self_url(&uri, &headers)),
+    ))
+}
+
+/// Like [`index`], but as a JSON Feed; see [`json::render`] for the extensions.
+pub async fn json(
+    State(state): State<Arc<AppState>>,
+    uri: Uri,
+    headers: HeaderMap,
+    RawQuery(params): RawQuery,
+) -> Result<impl IntoResponse, ResponseError> {
+    let (feeds, options) = query_feeds(params)?;
+
+    let aggregate = aggregate(&state, feeds, &options).await?;
+    rendered(&state, "json", [&aggregate]);
+    Ok((
+        status([&aggregate]),
+        [(CONTENT_TYPE, "application/feed+json")],
+        json::render(&aggregate, &self_url(&uri, &headers)),
+    ))
+}
+
+/// List the collections defined in the config file.
+pub async fn collections(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let mut html = String::from(
+        r#"<!DOCTYPE html><html><head><meta charset="utf-8"></head><body><h1>Collections</h1><ul>"#,
+    );
+    for (name, collection) in &state.config.collections {
+        html.push_str(&format!(
+            r#"<li><a href="{}">{}</a> ({} feeds)</li>"#,
+            Attr(&utf8_percent_encode(name, NON_ALPHANUMERIC).to_string()),
+            Text(name),
+            collection.feeds.len()
+        ));
     }
+    html.push_str("</ul></body></html>");
+    Html(html)
+}
+
+/// Load a collection of feeds defined in the config file, like [`index`].
+///
+/// Options and additional feed URLs may be provided as query params.
+pub async fn collection(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    RawQuery(params): RawQuery,
+) -> Result<impl IntoResponse, ResponseError> {
+    let (feeds, options) = collection_feeds(&state, name, params)?;
 
-    // Collect all items into one vec, sorted by date.
-    let mut all_items = Vec::new();
-    for (feed, items) in &mut all_feeds {
-        // Carry along a reference to each item's feed.
-        all_items.extend(items.drain(..).map(|item| (&*feed, item)));
+    let aggregate = aggregate(&state, feeds, &options).await?;
+    rendered(&state, "html", [&aggregate]);
+    Ok((status([&aggregate]), Html(render(aggregate))))
+}
+
+/// Like [`collection`], but as an Atom feed.
+pub async fn collection_atom(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    uri: Uri,
+    headers: HeaderMap,
+    RawQuery(params): RawQuery,
+) -> Result<impl IntoResponse, ResponseError> {
+    let (feeds, options) = collection_feeds(&state, name, params)?;
+
+    let aggregate = aggregate(&state, feeds, &options).await?;
+    rendered(&state, "atom", [&aggregate]);
+    Ok((
+        status([&aggregate]),
+        [(CONTENT_TYPE, "application/atom+xml; charset=utf-8")],
+        atom::render(&aggregate, &self_url(&uri, &headers)),
+    ))
+}
+
+/// Like [`collection`], but as a JSON Feed.
+pub async fn collection_json(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    uri: Uri,
+    headers: HeaderMap,
+    RawQuery(params): RawQuery,
+) -> Result<impl IntoResponse, ResponseError> {
+    let (feeds, options) = collection_feeds(&state, name, params)?;
+
+    let aggregate = aggregate(&state, feeds, &options).await?;
+    rendered(&state, "json", [&aggregate]);
+    Ok((
+        status([&aggregate]),
+        [(CONTENT_TYPE, "application/feed+json")],
+        json::render(&aggregate, &self_url(&uri, &headers)),
+    ))
+}
+
+/// Load the feeds listed in OPML documents, whose URLs are provided as query params like [`index`].
+///
+/// Feeds are grouped by the folders they're in.
+pub async fn opml(
+    State(state): State<Arc<AppState>>,
+    RawQuery(params): RawQuery,
+) -> Result<impl IntoResponse, ResponseError> {
+    let query::Query { urls, options } =
+        query::parse(params.as_deref().unwrap_or_default()).map_err(ResponseError::bad_request)?;
+    if urls.is_empty() {
+        return Err(ResponseError::bad_request(GetError::NoUrls));
     }
-    all_items.sort_by_key(|(_, item)| cmp::Reverse(item.timestamp));
 
-    // Drop items that point to a prefix of the feed URL.
-    all_items.retain(|(feed, item)| {
-        let drop = feed.url.starts_with(url::prefix(&item.href));
-        if drop {
-            // This happens for some unimportant GitHub events, e.g. branch deletion,
-            // which just point to the GitHub homepage.
-            tracing::debug!("dropping item: {:#?}", item);
-        }
-        !drop
-    });
+    // Problems with the OPML documents themselves are the fault of the servers they came from.
+    let mut outlines = Vec::new();
+    for url in urls {
+        let document = fetch::document(&state.fetcher, url)
+            .await
+            .map_err(|e| ResponseError::new(StatusCode::BAD_GATEWAY, e))?;
+        outlines.extend(
+            opml::parse(&document).map_err(|e| ResponseError::new(StatusCode::BAD_GATEWAY, e))?,
+        );
+    }
+    let groups =
+        outline_groups(outlines).map_err(|e| ResponseError::new(StatusCode::BAD_GATEWAY, e))?;
+
+    let groups = aggregate_groups(&state, groups, &options).await?;
+    rendered(
+        &state,
+        "html",
+        groups.iter().map(|(_, aggregate)| aggregate),
+    );
+    Ok((
+        status(groups.iter().map(|(_, aggregate)| aggregate)),
+        Html(render_groups(groups)),
+    ))
+}
+
+/// Like [`opml`], but with the OPML document uploaded as the request body.
+///
+/// Options may be provided as query params.
+pub async fn opml_upload(
+    State(state): State<Arc<AppState>>,
+    RawQuery(params): RawQuery,
+    body: Bytes,
+) -> Result<impl IntoResponse, ResponseError> {
+    let query::Query { urls, options } =
+        query::parse(params.as_deref().unwrap_or_default()).map_err(ResponseError::bad_request)?;
+    if !urls.is_empty() {
+        return Err(ResponseError::bad_request(GetError::UnexpectedUrls));
+    }
+
+    let outlines = opml::parse(&body).map_err(ResponseError::bad_request)?;
+    let groups = outline_groups(outlines).map_err(ResponseError::bad_request)?;
+
+    let groups = aggregate_groups(&state, groups, &options).await?;
+    rendered(
+        &state,
+        "html",
+        groups.iter().map(|(_, aggregate)| aggregate),
+    );
+    Ok((
+        status(groups.iter().map(|(_, aggregate)| aggregate)),
+        Html(render_groups(groups)),
+    ))
+}
+
+/// Export the feeds provided as query params, like [`index`], as an OPML document.
+pub async fn opml_export(
+    State(state): State<Arc<AppState>>,
+    RawQuery(params): RawQuery,
+) -> Result<impl IntoResponse, ResponseError> {
+    let (feeds, options) = query_feeds(params)?;
+
+    Ok(opml_response(&state, feeds, &options).await?)
+}
+
+/// Export a collection of feeds defined in the config file as an OPML document.
+pub async fn collection_opml(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+    RawQuery(params): RawQuery,
+) -> Result<impl IntoResponse, ResponseError> {
+    let (feeds, options) = collection_feeds(&state, name, params)?;
 
-    // Split items into one vec per day, and deduplicate them.
+    Ok(opml_response(&state, feeds, &options).await?)
+}
 
-    struct Day<'a> {
-        date: NaiveDate,
-        items: Vec<ItemsWithFeed<'a>>,
+/// Show how every feed fetched so far has fared, flagging ones that keep failing or have stopped publishing.
+pub async fn health(State(state): State<Arc<AppState>>) -> Html<String> {
+    Html(health::render(state.fetcher.health(), Utc::now()))
+}
+
+/// Serve metrics for Prometheus to scrape, if enabled with `--metrics`.
+pub async fn metrics(State(state): State<Arc<AppState>>) -> Result<Response, ResponseError> {
+    let metrics = state.metrics.as_ref().ok_or_else(|| {
+        ResponseError::new(
+            StatusCode::NOT_FOUND,
+            "metrics are disabled; enable them with --metrics",
+        )
+    })?;
+    Ok((
+        [(
+            CONTENT_TYPE,
+            "application/openmetrics-text; version=1.0.0; charset=utf-8",
+        )],
+        metrics.encode(),
+    )
+        .into_response())
+}
+
+/// Record each request's route, status and duration, when metrics are enabled.
+pub async fn track(
+    State(metrics): State<Arc<Metrics>>,
+    route: MatchedPath,
+    request: Request,
+    next: Next,
+) -> Response {
+    let start = Instant::now();
+    let method = request.method().clone();
+    let response = next.run(request).await;
+    metrics.request(&method, route.as_str(), response.status(), start.elapsed());
+    response
+}
+
+fn query_feeds(params: Option<String>) -> Result<(Feeds, query::Options), ResponseError> {
+    let query::Query { urls, options } =
+        query::parse(params.as_deref().unwrap_or_default()).map_err(ResponseError::bad_request)?;
+    if urls.is_empty() {
+        return Err(ResponseError::bad_request(GetError::NoUrls));
     }
 
-    struct ItemsWithFeed<'a> {
-        feed: &'a Feed,
-        item: Item,
-        count: usize,
-        highlighted: bool,
+    let feeds = urls.into_iter().map(|url| (url, None)).collect();
+
+    Ok((feeds, options))
+}
+
+fn collection_feeds(
+    state: &AppState,
+    name: String,
+    params: Option<String>,
+) -> Result<(Feeds, query::Options), ResponseError> {
+    let Some(collection) = state.config.collections.get(&name) else {
+        return Err(ResponseError::new(
+            StatusCode::NOT_FOUND,
+            GetError::NoSuchCollection(name),
+        ));
+    };
+    let query::Query { urls, options } =
+        query::parse(params.as_deref().unwrap_or_default()).map_err(ResponseError::bad_request)?;
+
+    let mut feeds = Vec::new();
+    for feed in &collection.feeds {
+        feeds.push((feed.url().parse()?, feed.label().map(str::to_owned)));
     }
+    feeds.extend(urls.into_iter().map(|url| (url, None)));
 
-    let mut days = Vec::<Day>::new();
+    Ok((feeds, options))
+}
 
-    {
-        let mut url_prefix_to_index = HashMap::<String, usize>::new();
-        for (feed, item) in all_items {
-            // Check whether we need to start a new day.
-            let date = item.timestamp.with_timezone(&Local).date_naive();
-            if days.last().map(|d| d.date) != Some(date) {
-                days.push(Day {
-                    date,
-                    items: Default::default(),
-                });
-                url_prefix_to_index.clear();
-            }
-            // Get or insert this item.
-            let day = days.last_mut().unwrap();
-            // Strip hash from the URL, so multiple links to the same page (but e.g. for different events with different anchors) are deduplicated.
-            let url_prefix = url::prefix(&item.href);
-            match url_prefix_to_index.entry(url_prefix.to_owned()) {
-                // If we've already seen this item, increment its count,
-                // and override the entry (so the oldest entry is used).
-                Entry::Occupied(entry) => {
-                    let index = *entry.get();
-                    day.items[index].item = item;
-                    day.items[index].count += 1;
-                }
-                // Otherwise, add a new item.
-                Entry::Vacant(entry) => {
-                    let index = day.items.len();
-                    day.items.push(ItemsWithFeed {
-                        feed,
-                        item,
-                        count: 1,
-                        highlighted: false,
-                    });
-                    entry.insert(index);
-                }
-            }
+/// Group OPML outlines by folder, in the order each folder first appears.
+fn outline_groups(outlines: Vec<opml::Outline>) -> Result<Vec<(String, Feeds)>, Error> {
+    let mut groups = Vec::<(String, Feeds)>::new();
+    for outline in outlines {
+        let feed = (outline.url.parse()?, outline.title);
+        match groups.iter_mut().find(|(name, _)| *name == outline.group) {
+            Some((_, feeds)) => feeds.push(feed),
+            None => groups.push((outline.group, vec![feed])),
         }
     }
 
-    // Highlight unique domains for the day.
-    {
-        let mut domain_to_entry_count = HashMap::<String, usize>::new();
-        for day in &mut days {
-            domain_to_entry_count.clear();
-
-            // Collect counts by domain.
-            for i in &mut day.items {
-                let domain = url::domain(&i.item.href);
-                *domain_to_entry_count.entry(domain.to_owned()).or_default() += 1;
-            }
+    Ok(groups)
+}
 
-            let max_count = domain_to_entry_count.values().max().copied().unwrap_or(0);
+/// Count the items about to be rendered, for metrics.
+fn rendered<'a>(
+    state: &AppState,
+    format: &'static str,
+    aggregates: impl IntoIterator<Item = &'a Aggregate>,
+) {
+    if let Some(metrics) = &state.metrics {
+        let items = aggregates
+            .into_iter()
+            .flat_map(|aggregate| &aggregate.days)
+            .map(|day| day.items.len())
+            .sum();
+        metrics.rendered(format, items);
+    }
+}
 
-            // Mark the items with the lowest count as highlighted.
-            for i in &mut day.items {
-                let domain = url::domain(&i.item.href);
-                let entry_count = domain_to_entry_count[domain];
-                // Highlight if there are fewer than 3 entries with this domain, and there are less entries than the most common domain.
-                i.highlighted = entry_count < 3 && entry_count < max_count;
-            }
+/// 502 if every feed failed, since then there's nothing to show but errors.
+fn status<'a>(aggregates: impl IntoIterator<Item = &'a Aggregate>) -> StatusCode {
+    let (mut loaded, mut failed) = (0, 0);
+    for aggregate in aggregates {
+        loaded += aggregate.feeds.len();
+        failed += aggregate.feed_errors.len();
+    }
+    if loaded == 0 && failed > 0 {
+        StatusCode::BAD_GATEWAY
+    } else {
+        StatusCode::OK
+    }
+}
+
+async fn opml_response(
+    state: &Arc<AppState>,
+    feeds: Feeds,
+    options: &query::Options,
+) -> Result<impl IntoResponse, Error> {
+    let urls = feeds.iter().map(|(url, _)| url.clone()).collect::<Vec<_>>();
+    let aggregate = aggregate(state, feeds, options).await?;
+
+    // Use each feed's own title if it loaded, and its new or discovered URL if it has one.
+    let feeds = urls
+        .into_iter()
+        .map(|url| {
+            let feed = aggregate
+                .feeds
+                .iter()
+                .find(|(u, _)| *u == url)
+                .map(|(_, feed)| &**feed);
+            let url = feed
+                .and_then(|f| f.moved_to.clone().or_else(|| f.discovered.clone()))
+                .unwrap_or_else(|| url.to_string());
+            (url, feed)
+        })
+        .collect::<Vec<_>>();
+
+    Ok((
+        [(CONTENT_TYPE, "text/x-opml; charset=utf-8")],
+        opml::render(&feeds),
+    ))
+}
+
+/// How to use katamari, for when no feeds are requested.
+fn landing(state: &AppState) -> Html<String> {
+    let mut html = String::from(
+        r#"<!DOCTYPE html>
+        <html>
+            <head><meta charset="utf-8"><title>katamari</title></head>
+            <body>
+                <h1>katamari</h1>
+                <p>Aggregate RSS, Atom and JSON feeds into one page, newest first.</p>
+                <form action="/" method="get">
+                    <input name="url" type="url" placeholder="https://example.com/feed.xml" size="50" required>
+                    <button>Load</button>
+                </form>
+                <h2>Usage</h2>
+                <ul>
+                    <li>List feed URLs after <code>/?</code>, separated by <code>&amp;</code>, e.g. <code>/?https://blog.rust-lang.org/feed.xml&amp;https://www.rust-lang.org/feeds/releases.xml</code></li>
+                    <li>Percent-encode URLs with their own query string in a <code>url=</code> param, e.g. <code>/?url=https%3A%2F%2Fexample.com%2Ffeed%3Fformat%3Drss</code></li>
+                    <li>Web pages work too, if they link to their feed.</li>
+                    <li>Mix in options: <code>days=7</code>, <code>limit=200</code>, <code>tz=Europe/Paris</code>, <code>highlight=off</code></li>
+                    <li>Get the same feeds as Atom at <code>/atom?…</code>, as JSON Feed at <code>/json?…</code>, or as an OPML list at <code>/opml/export?…</code></li>
+                    <li>Load every feed in an OPML file with <code>/opml?https://example.com/subscriptions.opml</code></li>
+                    <li>See which feeds keep failing or have gone quiet at <a href="/health">/health</a></li>
+                </ul>
+        "#,
+    );
+    if !state.config.collections.is_empty() {
+        html.push_str("<h2>Collections</h2><ul>");
+        for name in state.config.collections.keys() {
+            html.push_str(&format!(
+                r#"<li><a href="c/{}">{}</a></li>"#,
+                Attr(&utf8_percent_encode(name, NON_ALPHANUMERIC).to_string()),
+                Text(name)
+            ));
         }
+        html.push_str("</ul>");
     }
+    html.push_str("</body></html>");
+    Html(html)
+}
+
+/// The absolute URL of the current request, for feeds to refer to themselves.
+///
+/// Behind a TLS-terminating proxy, the scheme comes from its `X-Forwarded-Proto` header.
+fn self_url(uri: &Uri, headers: &HeaderMap) -> String {
+    let scheme = match headers
+        .get("x-forwarded-proto")
+        .and_then(|proto| proto.to_str().ok())
+        .and_then(|proto| proto.split(',').next())
+        .map(str::trim)
+    {
+        Some(proto) if proto.eq_ignore_ascii_case("https") => "https",
+        _ => "http",
+    };
+    match headers.get(HOST).and_then(|host| host.to_str().ok()) {
+        Some(host) => format!("{}://{}{}", scheme, host, uri),
+        None => uri.to_string(),
+    }
+}
+
+fn render(aggregate: Aggregate) -> String {
+    render_groups(vec![(String::new(), aggregate)])
+}
 
+/// Render several groups of feeds on one page, each in a collapsible section (unless it's unnamed).
+fn render_groups(groups: Vec<(String, Aggregate)>) -> String {
     let nonce = BASE64_URL_SAFE.encode(rand::random::<[u8; 16]>());
 
     let mut html = format!(
@@ -180,6 +488,10 @@ pub async fn index(
                     a:visited {{
                         color: color-mix(in lch, rgb(85, 26, 139), #fff)
                     }}
+                    summary {{
+                        font-size: 2em;
+                        font-weight: bold;
+                    }}
                 </style>
             </head>
             <body>
@@ -187,12 +499,99 @@ pub async fn index(
     "#,
     );
 
-    if !feed_errors.is_empty() {
+    for (name, aggregate) in groups {
+        if name.is_empty() {
+            render_aggregate(&mut html, aggregate);
+        } else {
+            html.push_str(&format!("<details open><summary>{}</summary>", Text(&name)));
+            render_aggregate(&mut html, aggregate);
+            html.push_str("</details>");
+        }
+    }
+
+    html
+}
+
+fn render_aggregate(html: &mut String, aggregate: Aggregate) {
+    let Aggregate {
+        feeds: _,
+        feed_errors,
+        feed_warnings,
+        moved_feeds,
+        discovered_feeds,
+        days,
+    } = aggregate;
+
+    if !feed_errors.is_empty() || !feed_warnings.is_empty() {
         html.push_str("<h1>Errors</h1>");
+
+        // Group errors by kind, so e.g. a DNS outage shows up as one list.
+        let mut by_kind = BTreeMap::<_, Vec<_>>::new();
         for (url, e) in feed_errors {
+            by_kind.entry(e.kind()).or_default().push((url, e));
+        }
+        for (kind, feed_errors) in by_kind {
+            html.push_str(&format!("<h2>{}</h2>", Text(kind.label())));
+            for (url, e) in feed_errors {
+                // Include an excerpt of the response body, since error pages often explain what went wrong.
+                let details = match &e {
+                    FetchError::Status { snippet, .. } if !snippet.is_empty() => {
+                        format!("<br/><sup>└ {}</sup>", Text(snippet))
+                    }
+                    _ => String::new(),
+                };
+                let url = url.to_string();
+                html.push_str(&format!(
+                    r#"<li><a class="error" href="{}">{}</a><br/><sup>└ {}</sup>{}</li>"#,
+                    Url(&url),
+                    Text(&url),
+                    Text(&e.to_string()),
+                    details
+                ));
+            }
+        }
+
+        if !feed_warnings.is_empty() {
+            html.push_str("<h2>Warnings</h2>");
+        }
+        for (url, warnings) in feed_warnings {
+            let url = url.to_string();
             html.push_str(&format!(
-                r#"<li><a class="error" href="{}">{}</a><br/><sup>└ {}</sup></li>"#,
-                url, url, e
+                r#"<li><a class="error" href="{}">{}</a>"#,
+                Url(&url),
+                Text(&url)
+            ));
+            for warning in warnings {
+                html.push_str(&format!("<br/><sup>└ {}</sup>", Text(&warning)));
+            }
+            html.push_str("</li>");
+        }
+    }
+
+    if !moved_feeds.is_empty() {
+        html.push_str("<h1>Moved</h1>");
+        for (url, moved_to) in moved_feeds {
+            let url = url.to_string();
+            html.push_str(&format!(
+                r#"<li><a class="error" href="{}">{}</a><br/><sup>└ permanently moved to <a href="{}">{}</a></sup></li>"#,
+                Url(&url),
+                Text(&url),
+                Url(&moved_to),
+                Text(&moved_to)
+            ));
+        }
+    }
+
+    if !discovered_feeds.is_empty() {
+        html.push_str("<h1>Discovered</h1>");
+        for (url, discovered) in discovered_feeds {
+            let url = url.to_string();
+            html.push_str(&format!(
+                r#"<li><a href="{}">{}</a><br/><sup>└ is a web page, using its feed <a href="{}">{}</a></sup></li>"#,
+                Url(&url),
+                Text(&url),
+                Url(&discovered),
+                Text(&discovered)
             ));
         }
     }
@@ -207,7 +606,8 @@ pub async fn index(
                 (
                     format!(
                         r#"<img src="{}" title="{}" loading="lazy"/> "#,
-                        thumbnail_url, i.feed.title
+                        Url(thumbnail_url),
+                        Attr(&i.feed.title)
                     ),
                     r#"<span class="spacer">&nbsp;</spacer>"#,
                 )
@@ -220,7 +620,7 @@ pub async fn index(
                 String::new()
             };
             let summary = if let Some(summary) = i.item.summary {
-                format!(r#"<br/>{}<sup>└ {}</sup>"#, spacer, summary)
+                format!(r#"<br/>{}<sup>└ {}</sup>"#, spacer, Text(&summary))
             } else {
                 String::new()
             };
@@ -228,13 +628,11 @@ pub async fn index(
                 r#"<li>{}<a class="{}" href="{}">{}</a>{}{}</li>"#,
                 thumbnail,
                 if i.highlighted { "highlight" } else { "" },
-                i.item.href,
-                i.item.title,
+                Url(&i.item.href),
+                Text(&i.item.title),
                 item_count,
                 summary
             ));
         }
     }
-
-    Ok(Html(html))
 }
@@ -0,0 +1,204 @@
+use super::*;
+use crate::fetch::{Feed, Item};
+use crate::server::aggregate::{Day, ItemsWithFeed};
+use chrono::{DateTime, NaiveDate, Utc};
+use std::time::Duration;
+
+fn feed(title: &str, logo_url: Option<&str>) -> Arc<Feed> {
+    Arc::new(Feed {
+        url: String::from("https://example.com/feed.xml"),
+        title: title.to_owned(),
+        logo_url: logo_url.map(str::to_owned),
+        ..Default::default()
+    })
+}
+
+fn item(href: &str, title: &str, summary: Option<&str>) -> Item {
+    Item {
+        timestamp: "2025-05-02T07:00:00Z".parse::<DateTime<Utc>>().unwrap(),
+        href: href.to_owned(),
+        title: title.to_owned(),
+        thumbnail_url: None,
+        summary: summary.map(str::to_owned),
+    }
+}
+
+fn render_items(feed: &Arc<Feed>, items: Vec<Item>) -> String {
+    let day = Day {
+        date: NaiveDate::from_ymd_opt(2025, 5, 2).unwrap(),
+        items: items
+            .into_iter()
+            .map(|item| ItemsWithFeed {
+                feed: Arc::clone(feed),
+                item,
+                count: 1,
+                highlighted: false,
+            })
+            .collect(),
+    };
+    render(Aggregate {
+        days: vec![day],
+        ..Default::default()
+    })
+}
+
+#[test]
+fn render_escapes_item_title() {
+    let feed = feed("Feed", None);
+    let html = render_items(
+        &feed,
+        vec![item(
+            "https://example.com/1",
+            "<script>alert(1)</script><a href=\"https://evil.example\">click</a>",
+            None,
+        )],
+    );
+
+    assert!(!html.contains("<script>"));
+    assert!(!html.contains("<a href=\"https://evil.example\">"));
+    assert!(html.contains("&lt;script&gt;alert(1)&lt;/script&gt;"));
+}
+
+#[test]
+fn render_escapes_item_href() {
+    let feed = feed("Feed", None);
+    let html = render_items(
+        &feed,
+        vec![
+            item(
+                "https://example.com/\"><img src=x>",
+                "Breaks out of href",
+                None,
+            ),
+            item("javascript:alert(1)", "Script link", None),
+        ],
+    );
+
+    assert!(!html.contains("<img src=x>"));
+    assert!(html.contains(r#"href="https://example.com/&quot;&gt;&lt;img src=x&gt;""#));
+    assert!(!html.contains("javascript:"));
+}
+
+#[test]
+fn render_escapes_summary() {
+    let feed = feed("Feed", None);
+    let html = render_items(
+        &feed,
+        vec![item(
+            "https://example.com/1",
+            "Title",
+            Some("</sup></li></ul><h1>Injected</h1>"),
+        )],
+    );
+
+    assert!(!html.contains("<h1>Injected</h1>"));
+    assert!(html.contains("&lt;/sup&gt;&lt;/li&gt;&lt;/ul&gt;&lt;h1&gt;Injected&lt;/h1&gt;"));
+}
+
+#[test]
+fn render_escapes_feed_title_attribute() {
+    let feed = feed(
+        "\" onerror=\"alert(1)",
+        Some("https://example.com/logo.png\" onload=\"alert(2)"),
+    );
+    let html = render_items(&feed, vec![item("https://example.com/1", "Title", None)]);
+
+    assert!(!html.contains("\" onerror=\""));
+    assert!(!html.contains("\" onload=\""));
+    assert!(html.contains(r#"title="&quot; onerror=&quot;alert(1)""#));
+}
+
+#[test]
+fn render_escapes_errors() {
+    let html = render(Aggregate {
+        feed_errors: vec![(
+            Uri::from_static("https://example.com/feed.xml"),
+            FetchError::Parse(String::from("<b>bad</b> error")),
+        )],
+        feed_warnings: vec![(
+            Uri::from_static("https://example.com/other.xml"),
+            vec![String::from("Skipped entry <i>1</i>: Missing link")],
+        )],
+        ..Default::default()
+    });
+
+    assert!(!html.contains("<b>bad</b>"));
+    assert!(!html.contains("<i>1</i>"));
+    assert!(html.contains("&lt;b&gt;bad&lt;/b&gt; error"));
+    assert!(html.contains("Skipped entry &lt;i&gt;1&lt;/i&gt;: Missing link"));
+}
+
+#[test]
+fn render_groups_errors_by_kind() {
+    let timeout = || FetchError::Timeout(Duration::from_secs(10));
+    let html = render(Aggregate {
+        feed_errors: vec![
+            (
+                Uri::from_static("https://a.example.com/feed.xml"),
+                timeout(),
+            ),
+            (
+                Uri::from_static("https://b.example.com/feed.xml"),
+                FetchError::Dns {
+                    host: String::from("b.example.com"),
+                    message: String::from("no such host"),
+                },
+            ),
+            (
+                Uri::from_static("https://c.example.com/feed.xml"),
+                timeout(),
+            ),
+        ],
+        ..Default::default()
+    });
+
+    let position = |s| html.find(s).unwrap();
+    assert_eq!(html.matches("<h2>").count(), 2);
+    assert!(position("<h2>DNS lookup failed</h2>") < position("https://b.example.com"));
+    assert!(position("https://b.example.com") < position("<h2>Timed out</h2>"));
+    assert!(position("<h2>Timed out</h2>") < position("https://a.example.com"));
+    assert!(position("https://a.example.com") < position("https://c.example.com"));
+}
+
+#[test]
+fn status_bad_gateway_when_all_feeds_failed() {
+    let failed = || Aggregate {
+        feed_errors: vec![(
+            Uri::from_static("https://example.com/feed.xml"),
+            FetchError::Status {
+                status: StatusCode::INTERNAL_SERVER_ERROR,
+                snippet: String::new(),
+            },
+        )],
+        ..Default::default()
+    };
+    let loaded = Aggregate {
+        feeds: vec![(
+            Uri::from_static("https://example.com/other.xml"),
+            feed("Feed", None),
+        )],
+        ..Default::default()
+    };
+
+    assert_eq!(status([&failed()]), StatusCode::BAD_GATEWAY);
+    assert_eq!(status([&failed(), &failed()]), StatusCode::BAD_GATEWAY);
+    assert_eq!(status([&failed(), &loaded]), StatusCode::OK);
+    assert_eq!(status([&Aggregate::default()]), StatusCode::OK);
+}
+
+#[test]
+fn self_url_honors_forwarded_proto() {
+    let uri = Uri::from_static("/atom?url=https://example.com/feed.xml");
+    let mut headers = HeaderMap::new();
+    headers.insert(HOST, "katamari.example.com".parse().unwrap());
+    assert_eq!(
+        self_url(&uri, &headers),
+        "http://katamari.example.com/atom?url=https://example.com/feed.xml"
+    );
+
+    headers.insert("x-forwarded-proto", "https, http".parse().unwrap());
+    assert_eq!(
+        self_url(&uri, &headers),
+        "https://katamari.example.com/atom?url=https://example.com/feed.xml"
+    );
+}
@@ -0,0 +1,228 @@
+use crate::fetch::{Feed, Item};
+use crate::url;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::{fs, io};
+
+/// Maximum number of items kept per feed, after which the oldest are dropped.
+const MAX_ITEMS: usize = 1000;
+
+/// Every item seen in polled feeds, persisted to a JSON file so history outlives each feed's own window.
+pub struct Store {
+    path: PathBuf,
+    feeds: Mutex<BTreeMap<String, StoredFeed>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredFeed {
+    feed: Feed,
+    /// Items keyed by normalized link.
+    items: BTreeMap<String, Item>,
+    /// Why the latest poll failed, if it did.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl Store {
+    /// Load the store from `path`, or start an empty one if it doesn't exist yet.
+    pub fn load(path: PathBuf) -> Result<Self, io::Error> {
+        let feeds = match fs::read(&path) {
+            Ok(contents) => serde_json::from_slice(&contents)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e),
+        };
+        Ok(Self {
+            path,
+            feeds: Mutex::new(feeds),
+        })
+    }
+
+    /// A feed's latest metadata and all of its stored items, keyed by the URL it was requested from.
+    pub fn get(&self, url: &str) -> Option<(Feed, Vec<Item>)> {
+        let feeds = self.feeds.lock().unwrap();
+        let stored = feeds.get(url)?;
+        let mut feed = stored.feed.clone();
+        if let Some(error) = &stored.error {
+            feed.warnings.push(format!("Latest poll failed: {}", error));
+        }
+        Some((feed, stored.items.values().cloned().collect()))
+    }
+
+    /// Record a successful poll, adding new items and updating existing ones.
+    pub fn insert(&self, url: String, feed: Feed, items: Vec<Item>) {
+        let mut feeds = self.feeds.lock().unwrap();
+        let stored = feeds.entry(url).or_insert_with(|| StoredFeed {
+            feed: feed.clone(),
+            items: BTreeMap::new(),
+            error: None,
+        });
+        stored.feed = feed;
+        stored.error = None;
+        for item in items {
+            stored.items.insert(url::normalize(&item.href), item);
+        }
+
+        if stored.items.len() > MAX_ITEMS {
+            let mut by_age = stored
+                .items
+                .iter()
+                .map(|(key, item)| (item.timestamp, key.clone()))
+                .collect::<Vec<_>>();
+            by_age.sort();
+            for (_, key) in &by_age[..by_age.len() - MAX_ITEMS] {
+                stored.items.remove(key);
+            }
+        }
+    }
+
+    /// Record a failed poll, keeping the feed's previous items.
+    pub fn insert_error(&self, url: &str, error: String) {
+        if let Some(stored) = self.feeds.lock().unwrap().get_mut(url) {
+            stored.error = Some(error);
+        }
+    }
+
+    /// Serialize the store, to be written with [`write`].
+    pub fn to_json(&self) -> Vec<u8> {
+        serde_json::to_vec(&*self.feeds.lock().unwrap()).unwrap()
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Replace the store file atomically, so a crash never leaves it half-written.
+pub fn write(path: &Path, json: &[u8]) -> Result<(), io::Error> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    fs::write(&tmp, json)?;
+    fs::rename(&tmp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, TimeDelta, Utc};
+
+    fn feed(title: &str) -> Feed {
+        Feed {
+            url: String::from("https://example.com/feed.xml"),
+            title: String::from(title),
+            ..Default::default()
+        }
+    }
+
+    fn item(href: &str, title: &str, timestamp: DateTime<Utc>) -> Item {
+        Item {
+            timestamp,
+            href: String::from(href),
+            title: String::from(title),
+            thumbnail_url: None,
+            summary: None,
+        }
+    }
+
+    fn titles(items: &[Item]) -> Vec<&str> {
+        let mut titles = items.iter().map(|i| i.title.as_str()).collect::<Vec<_>>();
+        titles.sort();
+        titles
+    }
+
+    fn tmp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("katamari-{}-{}.json", name, std::process::id()))
+    }
+
+    #[test]
+    fn test_keeps_history() {
+        let store = Store::load(tmp_path("history")).unwrap();
+        let url = String::from("https://example.com/feed.xml");
+        let now = Utc::now();
+
+        store.insert(
+            url.clone(),
+            feed("Old title"),
+            vec![item("https://example.com/a", "A", now)],
+        );
+        store.insert(
+            url.clone(),
+            feed("New title"),
+            vec![
+                item("HTTPS://Example.com/a/#comments", "A (edited)", now),
+                item("https://example.com/b", "B", now),
+            ],
+        );
+
+        let (feed, items) = store.get(&url).unwrap();
+        assert_eq!(feed.title, "New title");
+        assert_eq!(titles(&items), ["A (edited)", "B"]);
+        assert!(store.get("https://example.com/other.xml").is_none());
+    }
+
+    #[test]
+    fn test_error_keeps_items() {
+        let store = Store::load(tmp_path("error")).unwrap();
+        let url = String::from("https://example.com/feed.xml");
+
+        store.insert(
+            url.clone(),
+            feed("Feed"),
+            vec![item("https://example.com/a", "A", Utc::now())],
+        );
+        store.insert_error(&url, String::from("HTTP 500"));
+
+        let (stored, items) = store.get(&url).unwrap();
+        assert_eq!(stored.warnings, ["Latest poll failed: HTTP 500"]);
+        assert_eq!(titles(&items), ["A"]);
+
+        store.insert(url.clone(), feed("Feed"), Vec::new());
+        assert!(store.get(&url).unwrap().0.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_drops_oldest() {
+        let store = Store::load(tmp_path("oldest")).unwrap();
+        let url = String::from("https://example.com/feed.xml");
+        let now = Utc::now();
+
+        let items = (0..MAX_ITEMS + 2)
+            .map(|i| {
+                let timestamp = now - TimeDelta::minutes(i as i64);
+                item(
+                    &format!("https://example.com/{}", i),
+                    &i.to_string(),
+                    timestamp,
+                )
+            })
+            .collect();
+        store.insert(url.clone(), feed("Feed"), items);
+
+        let (_, items) = store.get(&url).unwrap();
+        assert_eq!(items.len(), MAX_ITEMS);
+        assert!(!items.iter().any(|i| i.title == MAX_ITEMS.to_string()));
+        assert!(!items.iter().any(|i| i.title == (MAX_ITEMS + 1).to_string()));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let path = tmp_path("round-trip");
+        let url = String::from("https://example.com/feed.xml");
+
+        let store = Store::load(path.clone()).unwrap();
+        store.insert(
+            url.clone(),
+            feed("Feed"),
+            vec![item("https://example.com/a", "A", Utc::now())],
+        );
+        write(&path, &store.to_json()).unwrap();
+
+        let loaded = Store::load(path.clone()).unwrap();
+        fs::remove_file(&path).unwrap();
+        let (feed, items) = loaded.get(&url).unwrap();
+        assert_eq!(feed.title, "Feed");
+        assert_eq!(titles(&items), ["A"]);
+    }
+}
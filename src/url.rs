@@ -8,10 +8,108 @@ pub fn domain(url: &str) -> &str {
         .unwrap_or(url)
 }
 
+pub fn is_absolute(url: &str) -> bool {
+    url.starts_with("http://") || url.starts_with("https://")
+}
+
 pub fn prefix(url: &str) -> &str {
     url.split_once('#').map(|(prefix, _)| prefix).unwrap_or(url)
 }
 
+/// Normalize a link for comparison: lowercase the scheme and host, and drop the fragment and any trailing slash.
+pub fn normalize(url: &str) -> String {
+    let url = prefix(url);
+    let Some((scheme, rest)) = url.split_once("://") else {
+        return url.to_owned();
+    };
+    let (host, path) = rest.split_at(rest.find(['/', '?']).unwrap_or(rest.len()));
+    let path = path.strip_suffix('/').unwrap_or(path);
+    format!(
+        "{}://{}{}",
+        scheme.to_ascii_lowercase(),
+        host.to_ascii_lowercase(),
+        path
+    )
+}
+
+/// Resolve a possibly-relative reference (e.g. from a `Location` header) against an absolute base URL.
+pub fn resolve(base: &str, reference: &str) -> String {
+    // Absolute URL.
+    if has_scheme(reference) {
+        return reference.to_owned();
+    }
+
+    let (scheme, rest) = base.split_once("://").unwrap_or(("https", base));
+
+    // Scheme-relative URL.
+    if let Some(reference) = reference.strip_prefix("//") {
+        return format!("{scheme}://{reference}");
+    }
+
+    let domain = domain(base);
+    let path = &rest[domain.len()..];
+
+    // Absolute path.
+    if reference.starts_with('/') {
+        return join(scheme, domain, reference);
+    }
+
+    // Same document, or a different fragment of it.
+    if reference.is_empty() || reference.starts_with('#') {
+        return format!("{}{reference}", prefix(base));
+    }
+
+    let path = prefix(path);
+    let path = path.split_once('?').map(|(path, _)| path).unwrap_or(path);
+
+    // Query-only reference.
+    if reference.starts_with('?') {
+        return format!("{scheme}://{domain}{path}{reference}");
+    }
+
+    // Relative path, replacing the last segment of the base path.
+    let dir = path.rsplit_once('/').map(|(dir, _)| dir).unwrap_or("");
+    join(scheme, domain, &format!("{dir}/{reference}"))
+}
+
+/// Whether a reference starts with a scheme (e.g. `https:`), before any `/`, `?` or `#`.
+fn has_scheme(reference: &str) -> bool {
+    let Some((scheme, _)) = reference.split_once(':') else {
+        return false;
+    };
+    scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+}
+
+/// Build a URL from an absolute path (with any query and fragment), resolving its `.` and `..` segments.
+fn join(scheme: &str, domain: &str, target: &str) -> String {
+    let (path, rest) = target.split_at(target.find(['?', '#']).unwrap_or(target.len()));
+    format!("{scheme}://{domain}{}{rest}", remove_dot_segments(path))
+}
+
+/// Remove `.` and `..` segments from an absolute path, as in RFC 3986 section 5.2.4.
+fn remove_dot_segments(path: &str) -> String {
+    let segments = path.split('/').skip(1).collect::<Vec<_>>();
+    let mut output = Vec::new();
+    for (i, segment) in segments.iter().enumerate() {
+        let last = i == segments.len() - 1;
+        match *segment {
+            "." => {}
+            ".." => {
+                output.pop();
+            }
+            segment => output.push(segment),
+        }
+        // A trailing `.` or `..` still refers to a directory.
+        if last && matches!(*segment, "." | "..") {
+            output.push("");
+        }
+    }
+    format!("/{}", output.join("/"))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -24,6 +122,15 @@ mod tests {
         assert_eq!(domain("https://example.com/foo/bar"), "example.com");
     }
 
+    #[test]
+    fn test_is_absolute() {
+        assert!(is_absolute("https://example.com/post/1"));
+        assert!(is_absolute("http://example.com"));
+        assert!(!is_absolute("tag:example.com,2025:post-1"));
+        assert!(!is_absolute("/post/1"));
+        assert!(!is_absolute(""));
+    }
+
     #[test]
     fn test_prefix() {
         assert_eq!(prefix("https://example.com"), "https://example.com");
@@ -42,4 +149,70 @@ mod tests {
             "https://example.com/test/2"
         );
     }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(
+            normalize("HTTPS://Example.COM/Post/1/#comments"),
+            "https://example.com/Post/1"
+        );
+        assert_eq!(normalize("https://example.com/"), "https://example.com");
+        assert_eq!(
+            normalize("https://example.com?Page=1"),
+            "https://example.com?Page=1"
+        );
+        assert_eq!(
+            normalize("tag:example.com,2025:1"),
+            "tag:example.com,2025:1"
+        );
+    }
+
+    #[test]
+    fn test_resolve() {
+        let base = "https://example.com/feeds/rss.xml?a=b";
+        assert_eq!(
+            resolve(base, "http://example.net/feed"),
+            "http://example.net/feed"
+        );
+        assert_eq!(
+            resolve(base, "//example.net/feed"),
+            "https://example.net/feed"
+        );
+        assert_eq!(resolve(base, "/atom.xml"), "https://example.com/atom.xml");
+        assert_eq!(
+            resolve(base, "atom.xml"),
+            "https://example.com/feeds/atom.xml"
+        );
+        assert_eq!(
+            resolve(base, "?c=d"),
+            "https://example.com/feeds/rss.xml?c=d"
+        );
+        assert_eq!(
+            resolve(base, "#top"),
+            "https://example.com/feeds/rss.xml?a=b#top"
+        );
+        assert_eq!(resolve(base, ""), "https://example.com/feeds/rss.xml?a=b");
+        assert_eq!(
+            resolve("https://example.com", "feed"),
+            "https://example.com/feed"
+        );
+        assert_eq!(
+            resolve(base, "/login?next=https://example.com/feed"),
+            "https://example.com/login?next=https://example.com/feed"
+        );
+        assert_eq!(
+            resolve(base, "feed?from=http://example.net"),
+            "https://example.com/feeds/feed?from=http://example.net"
+        );
+        assert_eq!(resolve(base, "../atom.xml"), "https://example.com/atom.xml");
+        assert_eq!(
+            resolve(base, "./a/../b/./atom.xml?x=../y"),
+            "https://example.com/feeds/b/atom.xml?x=../y"
+        );
+        assert_eq!(resolve(base, "/a/b/.."), "https://example.com/a/");
+        assert_eq!(
+            resolve(base, "../../../atom.xml"),
+            "https://example.com/atom.xml"
+        );
+    }
 }